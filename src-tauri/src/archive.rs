@@ -0,0 +1,63 @@
+use std::fs;
+use std::path::Path;
+
+use crate::get_margherita_dir;
+use crate::lock::ensure_unlocked;
+use crate::mode::ensure_editable;
+use crate::vault::{relative_path, resolve_in_vault, unique_path};
+
+// Archived notes keep their relative structure under this folder
+pub(crate) const ARCHIVE_DIR: &str = "archive";
+
+fn move_note(from: &Path, to: &Path) -> Result<(), String> {
+    ensure_editable()?;
+    if let Some(parent) = to.parent() {
+        fs::create_dir_all(parent).map_err(|e| format!("Failed to create directory: {}", e))?;
+    }
+    fs::rename(from, to).map_err(|e| format!("Failed to move file: {}", e))
+}
+
+// Move a note into archive/, returning its new relative path
+#[tauri::command]
+pub async fn archive_file(path: String) -> Result<String, String> {
+    let dir = get_margherita_dir()?;
+    let source = resolve_in_vault(&path)?;
+
+    if source.starts_with(dir.join(ARCHIVE_DIR)) {
+        return Err(format!("File is already archived: {}", path));
+    }
+    if !source.is_file() {
+        return Err(format!("File not found: {}", path));
+    }
+    ensure_unlocked(&relative_path(&dir, &source))?;
+
+    let relative = source.strip_prefix(&dir).map_err(|e| e.to_string())?;
+    let target = unique_path(dir.join(ARCHIVE_DIR).join(relative));
+    move_note(&source, &target)?;
+
+    println!("Archived {:?} to {:?}", source, target);
+    Ok(relative_path(&dir, &target))
+}
+
+// Move an archived note back to where it came from, returning its new relative path
+#[tauri::command]
+pub async fn unarchive_file(path: String) -> Result<String, String> {
+    let dir = get_margherita_dir()?;
+    let archive_dir = dir.join(ARCHIVE_DIR);
+    let source = resolve_in_vault(&path)?;
+
+    let relative = source
+        .strip_prefix(&archive_dir)
+        .map_err(|_| format!("File is not archived: {}", path))?;
+    if !source.is_file() {
+        return Err(format!("File not found: {}", path));
+    }
+    ensure_unlocked(&relative_path(&dir, &source))?;
+
+    // Another note may have taken the original name in the meantime
+    let target = unique_path(dir.join(relative));
+    move_note(&source, &target)?;
+
+    println!("Unarchived {:?} to {:?}", source, target);
+    Ok(relative_path(&dir, &target))
+}
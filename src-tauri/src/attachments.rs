@@ -0,0 +1,91 @@
+use serde::Serialize;
+use std::fs;
+use std::path::Path;
+
+use crate::get_margherita_dir;
+use crate::links::{markdown_links, resolve_link};
+use crate::markdown::LineIndex;
+use crate::vault::{is_attachment, relative_path, resolve_in_vault};
+
+// Attachments bigger than this are too much to hand to the webview in one piece
+const MAX_ATTACHMENT_BYTES: u64 = 20 * 1024 * 1024;
+
+#[derive(Debug, Serialize)]
+pub struct Attachment {
+    bytes: Vec<u8>,
+    mime: Option<String>,
+}
+
+// A local attachment reference that doesn't point at anything on disk
+#[derive(Debug, Serialize)]
+pub struct MissingAttachment {
+    dest: String,
+    line: usize,
+}
+
+// Sniff the type from the bytes; formats without a magic number fall back to the extension
+pub(crate) fn mime_type(path: &Path, bytes: &[u8]) -> Option<String> {
+    if let Some(kind) = infer::get(bytes) {
+        return Some(kind.mime_type().to_string());
+    }
+    let mime = match path.extension()?.to_str()?.to_ascii_lowercase().as_str() {
+        "svg" => "image/svg+xml",
+        "txt" | "md" | "markdown" => "text/plain",
+        "csv" => "text/csv",
+        "json" => "application/json",
+        _ => return None,
+    };
+    Some(mime.to_string())
+}
+
+// A file's raw bytes and MIME type, so the preview can show it as a data URL
+#[tauri::command]
+pub async fn read_attachment(path: String) -> Result<Attachment, String> {
+    let file_path = resolve_in_vault(&path)?;
+    let meta = fs::metadata(&file_path).map_err(|e| format!("Failed to read attachment: {}", e))?;
+    if !meta.is_file() {
+        return Err(format!("Not a file: {}", path));
+    }
+    if meta.len() > MAX_ATTACHMENT_BYTES {
+        return Err(format!(
+            "Attachment is too large ({} bytes, at most {}): {}",
+            meta.len(),
+            MAX_ATTACHMENT_BYTES,
+            path
+        ));
+    }
+
+    let bytes = fs::read(&file_path).map_err(|e| format!("Failed to read attachment: {}", e))?;
+    let mime = mime_type(&file_path, &bytes);
+    Ok(Attachment { bytes, mime })
+}
+
+// Attachment and image references in a saved note whose files don't exist. Meant to run
+// right after a save, so typos surface while the user is still writing
+#[tauri::command]
+pub async fn check_attachments(path: String) -> Result<Vec<MissingAttachment>, String> {
+    let dir = get_margherita_dir()?;
+    let file_path = resolve_in_vault(&path)?;
+    let content =
+        fs::read_to_string(&file_path).map_err(|e| format!("Failed to read file: {}", e))?;
+    let note = relative_path(&dir, &file_path);
+    let lines = LineIndex::new(&content);
+
+    let mut missing = Vec::new();
+    for link in markdown_links(&content) {
+        let Some(target) = resolve_link(&note, &link.dest) else {
+            continue;
+        };
+        if is_attachment(Path::new(&target)) && !dir.join(&target).exists() {
+            missing.push(MissingAttachment {
+                dest: link.dest,
+                line: lines.line(link.range.start),
+            });
+        }
+    }
+
+    if !missing.is_empty() {
+        println!("{} has {} missing attachments", note, missing.len());
+    }
+    Ok(missing)
+}
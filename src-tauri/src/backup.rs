@@ -0,0 +1,365 @@
+use serde::Serialize;
+use std::collections::BTreeSet;
+use std::fs::{self, File};
+use std::io::{self, Read, Write};
+use std::path::{Path, PathBuf};
+
+use zip::write::SimpleFileOptions;
+use zip::{CompressionMethod, ZipArchive, ZipWriter};
+
+use crate::checksum::hash_file;
+use crate::get_margherita_dir;
+use crate::links::{markdown_links, resolve_link};
+use crate::lock::locked_notes;
+use crate::mode::ensure_editable;
+use crate::trash::move_to_trash;
+use crate::util::now_millis;
+use crate::vault::{
+    collect_notes, ensure_inside_vault, is_attachment, is_note, relative_path, resolve_in_vault,
+    unique_path, write_atomic_from,
+};
+
+// What restoring a backup would do, by vault-relative path
+#[derive(Debug, Default, Serialize)]
+pub struct RestorePlan {
+    added: Vec<String>,
+    overwritten: Vec<String>,
+    unchanged: Vec<String>,
+    // Entries that would be left out: unsafe names, unsupported files, locked notes
+    skipped: Vec<String>,
+    // Entries that can't be restored, with why, e.g. a folder sitting where the file goes
+    failed: Vec<(String, String)>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum RestoreAction {
+    Add,
+    Overwrite,
+    Unchanged,
+}
+
+// A zip entry that passed the checks, with what restoring it would do
+struct PlannedEntry {
+    index: usize,
+    path: String,
+    action: RestoreAction,
+}
+
+// Attachments referenced from a note's markdown links that exist in the vault
+fn referenced_attachments(dir: &Path, note: &str, content: &str) -> Vec<String> {
+    markdown_links(content)
+        .into_iter()
+        .filter_map(|link| resolve_link(note, &link.dest))
+        .filter(|target| {
+            let path = dir.join(target);
+            is_attachment(&path) && path.is_file()
+        })
+        .collect()
+}
+
+// Bundle notes into a zip, keeping their vault-relative paths; an empty
+// selection exports the whole vault. Returns the paths that were included
+#[tauri::command]
+pub async fn export_zip(
+    paths: Vec<String>,
+    out_path: String,
+    include_attachments: Option<bool>,
+) -> Result<Vec<String>, String> {
+    let dir = get_margherita_dir()?;
+
+    let notes: Vec<String> = if paths.is_empty() {
+        collect_notes(&dir)?
+            .iter()
+            .map(|p| relative_path(&dir, p))
+            .collect()
+    } else {
+        let mut notes = Vec::new();
+        for path in &paths {
+            let file_path = resolve_in_vault(path)?;
+            if !file_path.is_file() || !is_note(&file_path) {
+                return Err(format!("Not a note in the vault: {}", path));
+            }
+            notes.push(relative_path(&dir, &file_path));
+        }
+        notes
+    };
+
+    // A set keeps attachments shared by several notes from being added twice
+    let mut included: BTreeSet<String> = BTreeSet::new();
+    for note in &notes {
+        included.insert(note.clone());
+        if include_attachments.unwrap_or(false) {
+            let content = fs::read_to_string(dir.join(note))
+                .map_err(|e| format!("Failed to read {}: {}", note, e))?;
+            included.extend(referenced_attachments(&dir, note, &content));
+        }
+    }
+
+    let file = File::create(&out_path).map_err(|e| format!("Failed to create zip: {}", e))?;
+    let mut zip = ZipWriter::new(file);
+    let options = SimpleFileOptions::default().compression_method(CompressionMethod::Deflated);
+
+    for path in &included {
+        let bytes =
+            fs::read(dir.join(path)).map_err(|e| format!("Failed to read {}: {}", path, e))?;
+        zip.start_file(path.as_str(), options)
+            .map_err(|e| format!("Failed to add {} to zip: {}", path, e))?;
+        zip.write_all(&bytes)
+            .map_err(|e| format!("Failed to write {} to zip: {}", path, e))?;
+    }
+
+    zip.finish()
+        .map_err(|e| format!("Failed to finish zip: {}", e))?;
+    println!("Exported {} files to {}", included.len(), out_path);
+
+    Ok(included.into_iter().collect())
+}
+
+// Turn a zip entry name into a safe vault-relative path. Entries that would
+// escape the vault (zip-slip) or live in hidden folders are rejected
+fn sanitize_entry_name(name: &str) -> Option<PathBuf> {
+    let mut path = PathBuf::new();
+    for part in name.split(['/', '\\']) {
+        match part {
+            "" | "." => continue,
+            ".." => return None,
+            _ if part.starts_with('.') || part == "__MACOSX" => return None,
+            _ => {}
+        }
+        let cleaned: String = part
+            .chars()
+            .map(|c| match c {
+                '<' | '>' | ':' | '"' | '|' | '?' | '*' => '-',
+                c if c.is_control() => '-',
+                c => c,
+            })
+            .collect();
+        path.push(cleaned.trim());
+    }
+    if path.as_os_str().is_empty() {
+        None
+    } else {
+        Some(path)
+    }
+}
+
+// Extract the notes and attachments in a zip into the vault, never overwriting
+// existing files. Returns the vault-relative paths that were written
+#[tauri::command]
+pub async fn import_zip(zip_path: String) -> Result<Vec<String>, String> {
+    ensure_editable()?;
+    let dir = get_margherita_dir()?;
+    let file = File::open(&zip_path).map_err(|e| format!("Failed to open zip: {}", e))?;
+    let mut archive = ZipArchive::new(file).map_err(|e| format!("Failed to read zip: {}", e))?;
+
+    let mut imported = Vec::new();
+    for i in 0..archive.len() {
+        let mut entry = archive
+            .by_index(i)
+            .map_err(|e| format!("Failed to read zip entry: {}", e))?;
+        if entry.is_dir() {
+            continue;
+        }
+        let name = entry
+            .name()
+            .map_err(|e| format!("Invalid zip entry name: {}", e))?
+            .to_string();
+
+        let Some(relative) = sanitize_entry_name(&name) else {
+            println!("Skipping unsafe zip entry: {}", name);
+            continue;
+        };
+        if !is_note(&relative) && !is_attachment(&relative) {
+            println!("Skipping unsupported zip entry: {}", name);
+            continue;
+        }
+
+        let target = unique_path(dir.join(&relative));
+        ensure_inside_vault(&target)?;
+        if let Some(parent) = target.parent() {
+            fs::create_dir_all(parent).map_err(|e| format!("Failed to create folder: {}", e))?;
+        }
+        write_atomic_from(&target, &mut entry)
+            .map_err(|e| format!("Failed to extract {}: {}", name, e))?;
+
+        imported.push(relative_path(&dir, &target));
+    }
+
+    println!("Imported {} files from {}", imported.len(), zip_path);
+    Ok(imported)
+}
+
+fn open_zip(zip_path: &str) -> Result<ZipArchive<File>, String> {
+    let file = File::open(zip_path).map_err(|e| format!("Failed to open zip: {}", e))?;
+    ZipArchive::new(file).map_err(|e| format!("Failed to read zip: {}", e))
+}
+
+// Hex BLAKE3 hash of a zip entry, to compare with the file it would replace
+fn hash_entry(entry: &mut impl Read) -> Result<String, String> {
+    let mut hasher = blake3::Hasher::new();
+    io::copy(entry, &mut hasher).map_err(|e| format!("Failed to read zip entry: {}", e))?;
+    Ok(hasher.finalize().to_hex().to_string())
+}
+
+// Extract a zip entry into a temp file next to `target`, so the file it replaces is
+// only touched once the whole entry was read
+fn extract_to_temp(entry: &mut impl Read, target: &Path) -> Result<PathBuf, String> {
+    let file_name = target
+        .file_name()
+        .map(|n| n.to_string_lossy().into_owned())
+        .ok_or_else(|| format!("Invalid file path: {:?}", target))?;
+    let temp = target.with_file_name(format!(".{}.restore.tmp", file_name));
+    File::create(&temp)
+        .and_then(|mut out| io::copy(entry, &mut out))
+        .map_err(|e| {
+            let _ = fs::remove_file(&temp);
+            e.to_string()
+        })?;
+    Ok(temp)
+}
+
+// Work out, without extracting anything, what each entry of a backup would do to the
+// vault. Returns the plan along with the entries it covers
+fn plan_restore(
+    dir: &Path,
+    archive: &mut ZipArchive<File>,
+) -> Result<(RestorePlan, Vec<PlannedEntry>), String> {
+    let locked = locked_notes()?;
+    let mut plan = RestorePlan::default();
+    let mut actions = Vec::new();
+    let mut seen = BTreeSet::new();
+
+    for i in 0..archive.len() {
+        let mut entry = archive
+            .by_index(i)
+            .map_err(|e| format!("Failed to read zip entry: {}", e))?;
+        if entry.is_dir() {
+            continue;
+        }
+        let name = entry
+            .name()
+            .map_err(|e| format!("Invalid zip entry name: {}", e))?
+            .to_string();
+
+        let relative = match sanitize_entry_name(&name) {
+            Some(relative) if is_note(&relative) || is_attachment(&relative) => relative,
+            _ => {
+                plan.skipped.push(name);
+                continue;
+            }
+        };
+        let target = dir.join(&relative);
+        let relative = relative_path(dir, &target);
+        // A symlinked folder in the vault could still carry the file somewhere else
+        // A repeated entry would silently replace the first one, so only the first counts
+        if ensure_inside_vault(&target).is_err()
+            || locked.contains(&relative)
+            || !seen.insert(relative.clone())
+        {
+            plan.skipped.push(relative);
+            continue;
+        }
+
+        let action = if target.exists() && !target.is_file() {
+            plan.failed
+                .push((relative, "Not a file in the vault".to_string()));
+            continue;
+        } else if !target.exists() {
+            plan.added.push(relative.clone());
+            RestoreAction::Add
+        } else if target.is_file() && hash_file(&target)? == hash_entry(&mut entry)? {
+            plan.unchanged.push(relative.clone());
+            RestoreAction::Unchanged
+        } else {
+            plan.overwritten.push(relative.clone());
+            RestoreAction::Overwrite
+        };
+        actions.push(PlannedEntry {
+            index: i,
+            path: relative,
+            action,
+        });
+    }
+
+    Ok((plan, actions))
+}
+
+// Compare a backup with the vault before restoring it: which files it would add,
+// overwrite or leave as they are. Nothing is extracted
+#[tauri::command]
+pub async fn preview_restore(zip_path: String) -> Result<RestorePlan, String> {
+    let dir = get_margherita_dir()?;
+    let mut archive = open_zip(&zip_path)?;
+    Ok(plan_restore(&dir, &mut archive)?.0)
+}
+
+// Restore a backup over the vault. New files are always added; files that differ are
+// replaced only with `overwrite`, and the current version goes to the trash first.
+// Returns what was done, with skipped overwrites listed under `skipped`
+#[tauri::command]
+pub async fn apply_restore(zip_path: String, overwrite: bool) -> Result<RestorePlan, String> {
+    ensure_editable()?;
+    let dir = get_margherita_dir()?;
+    let mut archive = open_zip(&zip_path)?;
+    let (mut plan, actions) = plan_restore(&dir, &mut archive)?;
+    let batch = now_millis();
+
+    if !overwrite {
+        plan.skipped.append(&mut plan.overwritten);
+    }
+
+    for planned in actions {
+        let target = dir.join(&planned.path);
+        match planned.action {
+            RestoreAction::Unchanged => continue,
+            RestoreAction::Overwrite if !overwrite => continue,
+            _ => {}
+        }
+
+        // The vault may have changed since the plan was made
+        ensure_inside_vault(&target)?;
+        if target.exists() && !target.is_file() {
+            println!("Not restoring {}: not a file in the vault", planned.path);
+            plan.added.retain(|p| *p != planned.path);
+            plan.overwritten.retain(|p| *p != planned.path);
+            plan.failed
+                .push((planned.path, "Not a file in the vault".to_string()));
+            continue;
+        }
+        if let Some(parent) = target.parent() {
+            fs::create_dir_all(parent).map_err(|e| format!("Failed to create folder: {}", e))?;
+        }
+        let mut entry = archive
+            .by_index(planned.index)
+            .map_err(|e| format!("Failed to read zip entry: {}", e))?;
+        let temp = extract_to_temp(&mut entry, &target)
+            .map_err(|e| format!("Failed to extract {}: {}", planned.path, e))?;
+
+        let trashed = match planned.action {
+            RestoreAction::Overwrite => match move_to_trash(&dir, &target, batch) {
+                Ok(trashed) => Some(trashed),
+                Err(e) => {
+                    let _ = fs::remove_file(&temp);
+                    return Err(e);
+                }
+            },
+            _ => None,
+        };
+        if let Err(e) = fs::rename(&temp, &target) {
+            let _ = fs::remove_file(&temp);
+            if let Some(trashed) = trashed {
+                let _ = fs::rename(&trashed, &target);
+            }
+            return Err(format!("Failed to restore {}: {}", planned.path, e));
+        }
+    }
+
+    println!(
+        "Restored {} files from {} ({} added, {} overwritten)",
+        plan.added.len() + plan.overwritten.len(),
+        zip_path,
+        plan.added.len(),
+        plan.overwritten.len()
+    );
+    Ok(plan)
+}
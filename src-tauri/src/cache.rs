@@ -0,0 +1,108 @@
+use std::collections::hash_map::DefaultHasher;
+use std::collections::HashMap;
+use std::hash::{Hash, Hasher};
+use std::sync::Mutex;
+
+use tauri::State;
+
+use crate::markdown::ParseOptions;
+use crate::settings::SettingsState;
+
+// Rendered documents kept around for instant re-display
+const CAPACITY: usize = 64;
+
+struct Entry {
+    html: String,
+    last_used: u64,
+}
+
+#[derive(Default)]
+struct CacheInner {
+    entries: HashMap<u64, Entry>,
+    tick: u64,
+    // At most one entry (the open note) is exempt from eviction
+    pinned: Option<u64>,
+}
+
+#[derive(Default)]
+pub struct RenderCache {
+    inner: Mutex<CacheInner>,
+}
+
+// Key covering both the input and the options it was rendered with
+pub(crate) fn cache_key(input: &str, options: &ParseOptions) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    input.hash(&mut hasher);
+    serde_json::to_string(options)
+        .unwrap_or_default()
+        .hash(&mut hasher);
+    hasher.finish()
+}
+
+fn parse_key(input_hash: &str) -> Result<u64, String> {
+    u64::from_str_radix(input_hash, 16).map_err(|_| format!("Invalid cache key: {}", input_hash))
+}
+
+impl RenderCache {
+    pub(crate) fn get(&self, key: u64) -> Option<String> {
+        let mut inner = self.inner.lock().ok()?;
+        inner.tick += 1;
+        let tick = inner.tick;
+        let entry = inner.entries.get_mut(&key)?;
+        entry.last_used = tick;
+        Some(entry.html.clone())
+    }
+
+    pub(crate) fn insert(&self, key: u64, html: String) {
+        let Ok(mut inner) = self.inner.lock() else {
+            return;
+        };
+        inner.tick += 1;
+        let last_used = inner.tick;
+        inner.entries.insert(key, Entry { html, last_used });
+
+        // Evict least recently used entries, never the pinned one
+        while inner.entries.len() > CAPACITY {
+            let pinned = inner.pinned;
+            let oldest = inner
+                .entries
+                .iter()
+                .filter(|(k, _)| Some(**k) != pinned)
+                .min_by_key(|(_, e)| e.last_used)
+                .map(|(k, _)| *k);
+            match oldest {
+                Some(k) => inner.entries.remove(&k),
+                None => break,
+            };
+        }
+    }
+}
+
+// The cache key parse_markdown uses for this input with the vault's options
+#[tauri::command]
+pub async fn render_cache_key(
+    input: String,
+    settings: State<'_, SettingsState>,
+) -> Result<String, String> {
+    let options = settings.get()?.parse_options;
+    Ok(format!("{:016x}", cache_key(&input, &options)))
+}
+
+// Keep one rendered document cached regardless of LRU pressure; replaces any earlier pin
+#[tauri::command]
+pub async fn pin_cache(input_hash: String, cache: State<'_, RenderCache>) -> Result<(), String> {
+    let key = parse_key(&input_hash)?;
+    let mut inner = cache.inner.lock().map_err(|e| e.to_string())?;
+    inner.pinned = Some(key);
+    Ok(())
+}
+
+#[tauri::command]
+pub async fn unpin_cache(input_hash: String, cache: State<'_, RenderCache>) -> Result<(), String> {
+    let key = parse_key(&input_hash)?;
+    let mut inner = cache.inner.lock().map_err(|e| e.to_string())?;
+    if inner.pinned == Some(key) {
+        inner.pinned = None;
+    }
+    Ok(())
+}
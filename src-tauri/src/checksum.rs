@@ -0,0 +1,99 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs::File;
+use std::io;
+use std::path::Path;
+use std::time::UNIX_EPOCH;
+
+use crate::get_margherita_dir;
+use crate::vault::{collect_notes, relative_path, resolve_in_vault, walk_notes};
+
+// Hex BLAKE3 hash of a file, streamed so large files aren't held in memory
+pub(crate) fn hash_file(path: &Path) -> Result<String, String> {
+    let mut file = File::open(path).map_err(|e| format!("Failed to read file: {}", e))?;
+    let mut hasher = blake3::Hasher::new();
+    io::copy(&mut file, &mut hasher).map_err(|e| format!("Failed to read file: {}", e))?;
+    Ok(hasher.finalize().to_hex().to_string())
+}
+
+#[tauri::command]
+pub async fn file_checksum(path: String) -> Result<String, String> {
+    let file_path = resolve_in_vault(&path)?;
+    if !file_path.is_file() {
+        return Err(format!("File not found: {}", path));
+    }
+    hash_file(&file_path)
+}
+
+// Checksums of every note in the vault, archive included, keyed by relative path
+#[tauri::command]
+pub async fn vault_checksums() -> Result<HashMap<String, String>, String> {
+    let dir = get_margherita_dir()?;
+    let mut checksums = HashMap::new();
+    for path in collect_notes(&dir)? {
+        checksums.insert(relative_path(&dir, &path), hash_file(&path)?);
+    }
+    Ok(checksums)
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ManifestEntry {
+    path: String,
+    size: u64,
+    // Milliseconds since the Unix epoch
+    modified: u64,
+    checksum: String,
+}
+
+// How this vault differs from another device's manifest; `added` notes exist only
+// here, `removed` ones only over there
+#[derive(Debug, Default, Serialize)]
+pub struct SyncPlan {
+    added: Vec<String>,
+    removed: Vec<String>,
+    modified: Vec<String>,
+    unchanged: Vec<String>,
+}
+
+// Every note in the vault, archive included, with its size, mtime and checksum,
+// from one walk over the vault
+#[tauri::command]
+pub async fn vault_manifest() -> Result<Vec<ManifestEntry>, String> {
+    let dir = get_margherita_dir()?;
+    walk_notes(&dir)?
+        .into_iter()
+        .map(|note| {
+            Ok(ManifestEntry {
+                path: relative_path(&dir, &note.path),
+                size: note.meta.len(),
+                modified: note
+                    .meta
+                    .modified()
+                    .ok()
+                    .and_then(|t| t.duration_since(UNIX_EPOCH).ok())
+                    .map_or(0, |d| d.as_millis() as u64),
+                checksum: hash_file(&note.path)?,
+            })
+        })
+        .collect()
+}
+
+// Compare this vault against a manifest taken elsewhere. Notes count as modified
+// when their checksums differ; mtimes are left to the UI since clocks drift
+#[tauri::command]
+pub async fn compare_manifests(other: Vec<ManifestEntry>) -> Result<SyncPlan, String> {
+    let mut theirs: HashMap<String, String> =
+        other.into_iter().map(|e| (e.path, e.checksum)).collect();
+    let mut plan = SyncPlan::default();
+
+    for entry in vault_manifest().await? {
+        match theirs.remove(&entry.path) {
+            None => plan.added.push(entry.path),
+            Some(checksum) if checksum == entry.checksum => plan.unchanged.push(entry.path),
+            Some(_) => plan.modified.push(entry.path),
+        }
+    }
+    plan.removed = theirs.into_keys().collect();
+    plan.removed.sort();
+    Ok(plan)
+}
@@ -0,0 +1,189 @@
+use std::collections::HashMap;
+use std::fs::{self, OpenOptions};
+use std::io::{Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::{Duration, Instant, SystemTime};
+
+use tauri::State;
+
+use crate::get_margherita_dir;
+use crate::lock::ensure_unlocked;
+use crate::mode::ensure_editable;
+use crate::settings::SettingsState;
+use crate::util::new_token;
+use crate::vault::{ensure_inside_vault, is_note, relative_path, resolve_for_write};
+
+// Temp files live in the vault (same filesystem) so the final rename is atomic
+const TEMP_PREFIX: &str = ".upload-";
+
+// Uploads with no chunk for this long are considered abandoned
+const UPLOAD_TTL: Duration = Duration::from_secs(10 * 60);
+
+struct Upload {
+    // Vault-relative, resolved again at commit in case things changed since
+    name: String,
+    temp: PathBuf,
+    last_write: Instant,
+}
+
+#[derive(Default)]
+pub struct ChunkedSaveState {
+    uploads: Mutex<HashMap<String, Upload>>,
+}
+
+// Drop idle uploads, plus stray temp files left behind by a previous run
+fn cleanup_abandoned(dir: &Path, uploads: &mut HashMap<String, Upload>) {
+    uploads.retain(|_, upload| {
+        let alive = upload.last_write.elapsed() < UPLOAD_TTL;
+        if !alive {
+            let _ = fs::remove_file(&upload.temp);
+        }
+        alive
+    });
+
+    let active: Vec<&PathBuf> = uploads.values().map(|u| &u.temp).collect();
+    let Ok(entries) = fs::read_dir(dir) else {
+        return;
+    };
+    for entry in entries.flatten() {
+        let path = entry.path();
+        let stale = entry
+            .metadata()
+            .and_then(|m| m.modified())
+            .ok()
+            .and_then(|t| SystemTime::now().duration_since(t).ok())
+            .is_some_and(|age| age >= UPLOAD_TTL);
+        if entry.file_name().to_string_lossy().starts_with(TEMP_PREFIX)
+            && stale
+            && !active.contains(&&path)
+        {
+            println!("Removing abandoned upload {:?}", path);
+            let _ = fs::remove_file(&path);
+        }
+    }
+}
+
+// Start a chunked save of a large note, returning the token for write_chunk/commit_save
+#[tauri::command]
+pub async fn begin_save(
+    name: String,
+    state: State<'_, ChunkedSaveState>,
+    settings: State<'_, SettingsState>,
+) -> Result<String, String> {
+    ensure_editable()?;
+    let filename = if !is_note(Path::new(&name)) {
+        format!("{}.{}", name, settings.get()?.default_extension)
+    } else {
+        name
+    };
+    let target = resolve_for_write(&filename)?;
+    let dir = get_margherita_dir()?;
+    let name = relative_path(&dir, &target);
+    ensure_unlocked(&name)?;
+
+    let token = new_token();
+    let temp = dir.join(format!("{}{}.tmp", TEMP_PREFIX, token));
+    fs::write(&temp, b"").map_err(|e| format!("Failed to start save: {}", e))?;
+
+    let mut uploads = state.uploads.lock().map_err(|e| e.to_string())?;
+    cleanup_abandoned(&dir, &mut uploads);
+    uploads.insert(
+        token.clone(),
+        Upload {
+            name,
+            temp,
+            last_write: Instant::now(),
+        },
+    );
+
+    println!("Started chunked save of {}", filename);
+    Ok(token)
+}
+
+// Write one chunk at `offset`; chunks may arrive in any order
+#[tauri::command]
+pub async fn write_chunk(
+    token: String,
+    bytes: Vec<u8>,
+    offset: u64,
+    state: State<'_, ChunkedSaveState>,
+) -> Result<(), String> {
+    let mut uploads = state.uploads.lock().map_err(|e| e.to_string())?;
+    let upload = uploads
+        .get_mut(&token)
+        .ok_or_else(|| "Unknown or expired save token".to_string())?;
+
+    let mut file = OpenOptions::new()
+        .write(true)
+        .open(&upload.temp)
+        .map_err(|e| format!("Failed to open temp file: {}", e))?;
+    file.seek(SeekFrom::Start(offset))
+        .and_then(|_| file.write_all(&bytes))
+        .map_err(|e| format!("Failed to write chunk: {}", e))?;
+
+    upload.last_write = Instant::now();
+    Ok(())
+}
+
+// Atomically move the finished upload into place, returning the saved path. The
+// lock, size limit and vault checks run again, since the upload may have taken a while
+#[tauri::command]
+pub async fn commit_save(
+    token: String,
+    state: State<'_, ChunkedSaveState>,
+    settings: State<'_, SettingsState>,
+) -> Result<String, String> {
+    let upload = state
+        .uploads
+        .lock()
+        .map_err(|e| e.to_string())?
+        .remove(&token)
+        .ok_or_else(|| "Unknown or expired save token".to_string())?;
+
+    let target = check_commit(&upload, &settings).inspect_err(|_| {
+        let _ = fs::remove_file(&upload.temp);
+    })?;
+    // Rename onto where a symlinked note points, so the link itself survives
+    let real = ensure_inside_vault(&target).inspect_err(|_| {
+        let _ = fs::remove_file(&upload.temp);
+    })?;
+    fs::rename(&upload.temp, &real).map_err(|e| {
+        let _ = fs::remove_file(&upload.temp);
+        format!("Failed to save file: {}", e)
+    })?;
+
+    println!("Saved file: {:?}", target);
+    Ok(target.to_string_lossy().into_owned())
+}
+
+// Where a finished upload may go, or why it can't
+fn check_commit(upload: &Upload, settings: &SettingsState) -> Result<PathBuf, String> {
+    ensure_editable()?;
+    ensure_unlocked(&upload.name)?;
+
+    // The same id, modified stamp and line endings save_file would give the note
+    let settings = settings.get()?;
+    let content =
+        fs::read_to_string(&upload.temp).map_err(|e| format!("Failed to read temp file: {}", e))?;
+    let prepared = settings.prepare_for_save(content.clone());
+    if prepared != content {
+        fs::write(&upload.temp, &prepared)
+            .map_err(|e| format!("Failed to write temp file: {}", e))?;
+    }
+
+    let limit = settings.max_note_bytes;
+    let size = prepared.len() as u64;
+    if limit > 0 && size > limit {
+        return Err(format!(
+            "{} is {} bytes, over the vault's limit of {}",
+            upload.name, size, limit
+        ));
+    }
+
+    let target = resolve_for_write(&upload.name)?;
+    if let Some(parent) = target.parent() {
+        fs::create_dir_all(parent).map_err(|e| format!("Failed to create directory: {}", e))?;
+    }
+    Ok(target)
+}
@@ -0,0 +1,273 @@
+use pulldown_cmark::{Alignment, Event, Options, Parser, Tag, TagEnd};
+use std::fs;
+use std::ops::Range;
+
+use crate::frontmatter::split_frontmatter;
+use crate::get_margherita_dir;
+use crate::links::apply_edits;
+use crate::vault::{active_notes, relative_path};
+
+// Notes with at most `threshold_bytes` of body text; frontmatter doesn't count
+#[tauri::command]
+pub async fn find_empty_notes(threshold_bytes: u64) -> Result<Vec<String>, String> {
+    let dir = get_margherita_dir()?;
+    let mut empty = Vec::new();
+
+    for path in active_notes(&dir)? {
+        let Ok(content) = fs::read_to_string(&path) else {
+            continue;
+        };
+        let (_, body) = split_frontmatter(&content);
+        if body.trim().len() as u64 <= threshold_bytes {
+            empty.push(relative_path(&dir, &path));
+        }
+    }
+
+    println!("Found {} empty notes", empty.len());
+    Ok(empty)
+}
+
+// Byte ranges of the code blocks in `body`, fenced or indented
+fn code_block_ranges(body: &str) -> Vec<Range<usize>> {
+    Parser::new_ext(body, Options::all())
+        .into_offset_iter()
+        .filter_map(|(event, range)| match event {
+            Event::Start(Tag::CodeBlock(_)) => Some(range),
+            _ => None,
+        })
+        .collect()
+}
+
+// Collapse runs of identical adjacent non-empty lines, e.g. left behind by a bad merge.
+// Code blocks and frontmatter are kept as they are, since repeats there can be deliberate
+#[tauri::command]
+pub async fn dedupe_consecutive_lines(content: String) -> Result<String, String> {
+    let (_, body) = split_frontmatter(&content);
+    let body_offset = content.len() - body.len();
+    let code = code_block_ranges(body);
+
+    let mut cleaned = String::with_capacity(content.len());
+    cleaned.push_str(&content[..body_offset]);
+    let mut previous: Option<&str> = None;
+    let mut offset = 0;
+
+    for line in body.split_inclusive('\n') {
+        let start = offset;
+        offset += line.len();
+        let text = line.trim_end_matches(['\r', '\n']);
+
+        if code.iter().any(|r| r.contains(&start)) {
+            previous = None;
+        } else if !text.trim().is_empty() && previous == Some(text) {
+            continue;
+        } else {
+            previous = Some(text);
+        }
+        cleaned.push_str(line);
+    }
+
+    // A dropped last line may have been the only one without a trailing newline
+    if !body.ends_with('\n') && cleaned.ends_with('\n') && cleaned.len() < content.len() {
+        cleaned.pop();
+        if cleaned.ends_with('\r') {
+            cleaned.pop();
+        }
+    }
+    Ok(cleaned)
+}
+
+// Shift every heading up so the shallowest one becomes H1, keeping their relative depth.
+// Setext headings stay setext; only their underline changes
+#[tauri::command]
+pub async fn normalize_heading_levels(content: String) -> Result<String, String> {
+    let (_, body) = split_frontmatter(&content);
+    let body_offset = content.len() - body.len();
+
+    let headings: Vec<(usize, Range<usize>)> = Parser::new_ext(body, Options::all())
+        .into_offset_iter()
+        .filter_map(|(event, range)| match event {
+            Event::Start(Tag::Heading { level, .. }) => Some((level as usize, range)),
+            _ => None,
+        })
+        .collect();
+    let Some(shift) = headings.iter().map(|(level, _)| level - 1).min() else {
+        return Ok(content);
+    };
+    if shift == 0 {
+        return Ok(content);
+    }
+
+    let mut edits = Vec::new();
+    for (level, range) in headings {
+        let source = &body[range.clone()];
+        let start = body_offset + range.start;
+        let atx = source.find('#').filter(|&i| {
+            let after = source[i..].trim_start_matches('#');
+            source[..i].trim().is_empty() && (after.is_empty() || after.starts_with([' ', '\t']))
+        });
+        if let Some(hashes) = atx {
+            // ATX: drop `shift` of the leading #s
+            let at = start + hashes;
+            edits.push((at..at + shift, String::new()));
+        } else if level == 2 {
+            // Setext: only H2 can move, to an `===` underline
+            let trimmed = source.trim_end();
+            if let Some(underline) = trimmed.rfind('\n').map(|i| i + 1) {
+                let dashes = trimmed[underline..].trim();
+                let at = start + underline + trimmed[underline..].find('-').unwrap_or(0);
+                edits.push((at..at + dashes.len(), "=".repeat(dashes.len())));
+            }
+        }
+    }
+
+    Ok(apply_edits(&content, edits))
+}
+
+// Cells of one table row, split on unescaped pipes with the outer pipes dropped
+fn table_cells(line: &str) -> Vec<String> {
+    let line = line.trim();
+    let line = line.strip_prefix('|').unwrap_or(line);
+    let line = match line.strip_suffix('|') {
+        Some(rest) if !rest.ends_with('\\') => rest,
+        _ => line,
+    };
+
+    let mut cells = Vec::new();
+    let mut cell = String::new();
+    let mut escaped = false;
+    for c in line.chars() {
+        if c == '|' && !escaped {
+            cells.push(cell.trim().to_string());
+            cell.clear();
+        } else {
+            cell.push(c);
+        }
+        escaped = c == '\\' && !escaped;
+    }
+    cells.push(cell.trim().to_string());
+    cells
+}
+
+fn is_delimiter_cell(cell: &str) -> bool {
+    let dashes = cell.trim_start_matches(':').trim_end_matches(':');
+    !dashes.is_empty() && dashes.chars().all(|c| c == '-')
+}
+
+fn pad_cell(cell: &str, width: usize, alignment: Alignment) -> String {
+    let padding = width.saturating_sub(cell.chars().count());
+    match alignment {
+        Alignment::Right => format!("{}{}", " ".repeat(padding), cell),
+        Alignment::Center => {
+            let left = padding / 2;
+            format!("{}{}{}", " ".repeat(left), cell, " ".repeat(padding - left))
+        }
+        Alignment::Left | Alignment::None => format!("{}{}", cell, " ".repeat(padding)),
+    }
+}
+
+fn delimiter_cell(width: usize, alignment: Alignment) -> String {
+    match alignment {
+        Alignment::Left => format!(":{}", "-".repeat(width - 1)),
+        Alignment::Right => format!("{}:", "-".repeat(width - 1)),
+        Alignment::Center => format!(":{}:", "-".repeat(width - 2)),
+        Alignment::None => "-".repeat(width),
+    }
+}
+
+// A table's lines laid out with aligned columns, or None unless every row has as many
+// cells as the delimiter row
+fn format_table(source: &str, alignments: &[Alignment], newline: &str) -> Option<String> {
+    let rows: Vec<Vec<String>> = source
+        .lines()
+        .filter(|l| !l.trim().is_empty())
+        .map(table_cells)
+        .collect();
+    let columns = alignments.len();
+    if rows.len() < 2
+        || rows.iter().any(|r| r.len() != columns)
+        || !rows[1].iter().all(|c| is_delimiter_cell(c))
+    {
+        return None;
+    }
+
+    // At least three wide, so the delimiter has room for its colons
+    let mut widths = vec![3; columns];
+    for row in rows.iter().take(1).chain(rows.iter().skip(2)) {
+        for (width, cell) in widths.iter_mut().zip(row) {
+            *width = (*width).max(cell.chars().count());
+        }
+    }
+
+    let mut formatted = String::with_capacity(source.len());
+    for (i, row) in rows.iter().enumerate() {
+        let cells: Vec<String> = if i == 1 {
+            widths
+                .iter()
+                .zip(alignments)
+                .map(|(&width, &alignment)| delimiter_cell(width, alignment))
+                .collect()
+        } else {
+            row.iter()
+                .zip(widths.iter().zip(alignments))
+                .map(|(cell, (&width, &alignment))| pad_cell(cell, width, alignment))
+                .collect()
+        };
+        formatted.push_str(&format!("| {} |{}", cells.join(" | "), newline));
+    }
+    Some(formatted)
+}
+
+// Re-lay GFM tables with padded, aligned columns, keeping each column's alignment marker.
+// Tables with ragged rows, or inside quotes and lists, are left exactly as written
+#[tauri::command]
+pub async fn format_tables(content: String) -> Result<String, String> {
+    let (_, body) = split_frontmatter(&content);
+    let body_offset = content.len() - body.len();
+    let newline = if content.contains("\r\n") {
+        "\r\n"
+    } else {
+        "\n"
+    };
+
+    let mut edits = Vec::new();
+    let mut containers = 0;
+    for (event, range) in Parser::new_ext(body, Options::ENABLE_TABLES).into_offset_iter() {
+        match event {
+            Event::Start(Tag::BlockQuote(_)) | Event::Start(Tag::Item) => containers += 1,
+            Event::End(TagEnd::BlockQuote(_)) | Event::End(TagEnd::Item) => containers -= 1,
+            Event::Start(Tag::Table(alignments)) if containers == 0 => {
+                // Whole lines, so any indent goes with the table
+                let start = body[..range.start].rfind('\n').map_or(0, |i| i + 1);
+                let source = &body[start..range.end];
+                let Some(mut formatted) = format_table(source, &alignments, newline) else {
+                    continue;
+                };
+                if !source.ends_with('\n') {
+                    formatted.truncate(formatted.len() - newline.len());
+                }
+                if formatted != source {
+                    edits.push((body_offset + start..body_offset + range.end, formatted));
+                }
+            }
+            _ => {}
+        }
+    }
+
+    Ok(apply_edits(&content, edits))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn duplicates_collapse_outside_code_only() {
+        let content = "Same line\nSame line\n\n```\nrepeat();\nrepeat();\n```\nAfter\nAfter\n";
+        let cleaned =
+            tauri::async_runtime::block_on(dedupe_consecutive_lines(content.to_string())).unwrap();
+        assert_eq!(
+            cleaned,
+            "Same line\n\n```\nrepeat();\nrepeat();\n```\nAfter\n"
+        );
+    }
+}
@@ -0,0 +1,101 @@
+use serde_json::json;
+use std::fs;
+use std::path::Path;
+use std::time::Duration;
+
+use tauri::State;
+
+use crate::convert::page_to_markdown;
+use crate::get_margherita_dir;
+use crate::mode::ensure_editable;
+use crate::settings::SettingsState;
+use crate::slug::slugify;
+use crate::vault::{is_note, relative_path, resolve_for_write, unique_path, write_atomic};
+
+// Pages bigger than this are refused rather than clipped
+const MAX_PAGE_BYTES: usize = 5 * 1024 * 1024;
+
+const FETCH_TIMEOUT: Duration = Duration::from_secs(20);
+
+async fn fetch_page(url: &str) -> Result<String, String> {
+    let client = reqwest::Client::builder()
+        .timeout(FETCH_TIMEOUT)
+        .build()
+        .map_err(|e| format!("Failed to create HTTP client: {}", e))?;
+
+    let mut response = client.get(url).send().await.map_err(|e| {
+        if e.is_timeout() {
+            format!("Timed out fetching {}", url)
+        } else {
+            format!("Failed to fetch {}: {}", url, e)
+        }
+    })?;
+    if !response.status().is_success() {
+        return Err(format!(
+            "Failed to fetch {}: HTTP {}",
+            url,
+            response.status()
+        ));
+    }
+    if response.content_length().unwrap_or(0) as usize > MAX_PAGE_BYTES {
+        return Err(format!("Page is too large to clip: {}", url));
+    }
+
+    // The server may not send a length, so keep counting while reading
+    let mut body = Vec::new();
+    while let Some(chunk) = response
+        .chunk()
+        .await
+        .map_err(|e| format!("Failed to read {}: {}", url, e))?
+    {
+        if body.len() + chunk.len() > MAX_PAGE_BYTES {
+            return Err(format!("Page is too large to clip: {}", url));
+        }
+        body.extend_from_slice(&chunk);
+    }
+
+    Ok(String::from_utf8_lossy(&body).into_owned())
+}
+
+// Save a web page as a new note, with its address in the frontmatter `source`
+#[tauri::command]
+pub async fn clip_url(
+    url: String,
+    name: Option<String>,
+    settings: State<'_, SettingsState>,
+) -> Result<String, String> {
+    ensure_editable()?;
+    let url = url.trim().to_string();
+    if !url.starts_with("http://") && !url.starts_with("https://") {
+        return Err(format!("Only http and https pages can be clipped: {}", url));
+    }
+    let extension = settings.get()?.default_extension;
+
+    let html = fetch_page(&url).await?;
+    let (title, markdown) = page_to_markdown(&html);
+
+    let name = match name.map(|n| n.trim().to_string()).filter(|n| !n.is_empty()) {
+        Some(name) if is_note(Path::new(&name)) => name,
+        Some(name) => format!("{}.{}", name, extension),
+        None => {
+            let slug = title.as_deref().map(slugify).unwrap_or_default();
+            let stem = if slug.is_empty() { "clipping" } else { &slug };
+            format!("{}.{}", stem, extension)
+        }
+    };
+
+    let dir = get_margherita_dir()?;
+    let target = unique_path(resolve_for_write(&name)?);
+    if let Some(parent) = target.parent() {
+        fs::create_dir_all(parent).map_err(|e| format!("Failed to create folder: {}", e))?;
+    }
+
+    let frontmatter = serde_yaml::to_string(&json!({ "source": url }))
+        .map_err(|e| format!("Failed to write frontmatter: {}", e))?;
+    let heading = title.map(|t| format!("# {}\n\n", t)).unwrap_or_default();
+    let content = format!("---\n{}---\n\n{}{}\n", frontmatter, heading, markdown);
+
+    write_atomic(&target, &content)?;
+    println!("Clipped {} to {:?}", url, target);
+    Ok(relative_path(&dir, &target))
+}
@@ -0,0 +1,67 @@
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::fs;
+use std::path::PathBuf;
+use std::sync::Mutex;
+
+// App state that isn't a user preference lives in the OS config directory
+fn config_path() -> Result<PathBuf, String> {
+    dirs::config_dir()
+        .map(|mut path| {
+            path.push("margherita");
+            path.push("config.json");
+            path
+        })
+        .ok_or_else(|| "Could not find config directory".to_string())
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub(crate) struct AppConfig {
+    // Last known word count per note, and words written per day (YYYY-MM-DD)
+    pub(crate) note_words: HashMap<String, usize>,
+    pub(crate) daily_words: BTreeMap<String, usize>,
+    pub(crate) words_checked_at: u64,
+    // How far down each note was scrolled, as a 0.0-1.0 fraction
+    pub(crate) scroll_positions: HashMap<String, f32>,
+    // Notes the user locked against accidental edits
+    pub(crate) locked_notes: BTreeSet<String>,
+    // When each note was last opened, in unix millis
+    pub(crate) opened_at: HashMap<String, u64>,
+    // The user's own stylesheet for exports, added after the theme or replacing it
+    pub(crate) export_css: String,
+    pub(crate) export_css_replaces_theme: bool,
+}
+
+// Serializes read-modify-write cycles on the config file
+static CONFIG_LOCK: Mutex<()> = Mutex::new(());
+
+fn load(path: &PathBuf) -> AppConfig {
+    fs::read_to_string(path)
+        .ok()
+        .and_then(|content| serde_json::from_str(&content).ok())
+        .unwrap_or_default()
+}
+
+pub(crate) fn read_config() -> Result<AppConfig, String> {
+    let _guard = CONFIG_LOCK.lock().map_err(|e| e.to_string())?;
+    Ok(load(&config_path()?))
+}
+
+// Load the config, let `f` change it, and write it back
+pub(crate) fn update_config<T>(f: impl FnOnce(&mut AppConfig) -> T) -> Result<T, String> {
+    let _guard = CONFIG_LOCK.lock().map_err(|e| e.to_string())?;
+    let path = config_path()?;
+    let mut config = load(&path);
+    let result = f(&mut config);
+
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)
+            .map_err(|e| format!("Failed to create config directory: {}", e))?;
+    }
+    let json = serde_json::to_string_pretty(&config)
+        .map_err(|e| format!("Failed to serialize config: {}", e))?;
+    fs::write(&path, json).map_err(|e| format!("Failed to save config: {}", e))?;
+
+    Ok(result)
+}
@@ -0,0 +1,83 @@
+// Elements whose contents should never end up in a note
+const STRIPPED_ELEMENTS: &[&str] = &["head", "script", "style", "noscript", "template"];
+
+// Remove whole elements (open tag through matching close tag), case-insensitively
+fn strip_elements(html: &str, tags: &[&str]) -> String {
+    let lower = html.to_ascii_lowercase();
+    let mut result = String::with_capacity(html.len());
+    let mut pos = 0;
+
+    'scan: while pos < html.len() {
+        let Some(next) = lower[pos..].find('<') else {
+            break;
+        };
+        let start = pos + next;
+
+        for tag in tags {
+            let open = format!("<{}", tag);
+            let after = lower.as_bytes().get(start + open.len()).copied();
+            if lower[start..].starts_with(&open)
+                && matches!(after, Some(b'>' | b' ' | b'\t' | b'\n' | b'\r' | b'/'))
+            {
+                result.push_str(&html[pos..start]);
+                let close = format!("</{}", tag);
+                pos = match lower[start..].find(&close) {
+                    Some(i) => {
+                        let close_start = start + i;
+                        lower[close_start..]
+                            .find('>')
+                            .map(|j| close_start + j + 1)
+                            .unwrap_or(html.len())
+                    }
+                    None => html.len(),
+                };
+                continue 'scan;
+            }
+        }
+
+        result.push_str(&html[pos..start + 1]);
+        pos = start + 1;
+    }
+
+    if pos < html.len() {
+        result.push_str(&html[pos..]);
+    }
+    result
+}
+
+// Page chrome that isn't part of an article's content
+const PAGE_CHROME: &[&str] = &["nav", "header", "footer", "aside", "form"];
+
+pub(crate) fn to_markdown(html: &str) -> String {
+    let cleaned = strip_elements(html, STRIPPED_ELEMENTS);
+    html2md::parse_html(&cleaned).trim().to_string()
+}
+
+// The inner HTML of the first `<tag>` element, if there is one
+fn element_contents<'a>(html: &'a str, lower: &str, tag: &str) -> Option<&'a str> {
+    let open = lower.find(&format!("<{}", tag))?;
+    let inner_start = open + lower[open..].find('>')? + 1;
+    let inner_end = inner_start + lower[inner_start..].find(&format!("</{}", tag))?;
+    Some(&html[inner_start..inner_end])
+}
+
+// A web page's title and the markdown of its main content: the <article> or
+// <main> element when there is one, otherwise the body without navigation
+pub(crate) fn page_to_markdown(html: &str) -> (Option<String>, String) {
+    let lower = html.to_ascii_lowercase();
+    let title = element_contents(html, &lower, "title")
+        .map(|t| html2md::parse_html(t).trim().to_string())
+        .filter(|t| !t.is_empty());
+
+    let main = element_contents(html, &lower, "article")
+        .or_else(|| element_contents(html, &lower, "main"))
+        .or_else(|| element_contents(html, &lower, "body"))
+        .unwrap_or(html);
+    (title, to_markdown(&strip_elements(main, PAGE_CHROME)))
+}
+
+// Convert pasted HTML into markdown suitable for inserting into a note
+#[tauri::command]
+pub async fn html_to_markdown(html: String) -> Result<String, String> {
+    Ok(to_markdown(&html))
+}
@@ -0,0 +1,137 @@
+use chardetng::{EncodingDetector, Iso2022JpDetection, Utf8Detection};
+use std::fs;
+
+use crate::get_margherita_dir;
+use crate::lock::ensure_unlocked;
+use crate::vault::{relative_path, resolve_in_vault, write_atomic};
+
+// UTF-8 text that was decoded as Windows-1252 somewhere along the way, and what it
+// should have been. Only sequences that are very unlikely in real prose are listed
+const MOJIBAKE: &[(&str, &str)] = &[
+    ("â€™", "’"),
+    ("â€˜", "‘"),
+    ("â€œ", "“"),
+    ("â€\u{9d}", "”"),
+    ("â€“", "–"),
+    ("â€”", "—"),
+    ("â€¦", "…"),
+    ("â€¢", "•"),
+    ("â‚¬", "€"),
+    ("Â\u{a0}", "\u{a0}"),
+    ("Â©", "©"),
+    ("Â®", "®"),
+    ("Â°", "°"),
+    ("Â«", "«"),
+    ("Â»", "»"),
+    ("Ã©", "é"),
+    ("Ã¨", "è"),
+    ("Ã\u{a0}", "à"),
+    ("Ã¡", "á"),
+    ("Ã¢", "â"),
+    ("Ã¤", "ä"),
+    ("Ã§", "ç"),
+    ("Ãª", "ê"),
+    ("Ã«", "ë"),
+    ("Ã\u{ad}", "í"),
+    ("Ã®", "î"),
+    ("Ã¯", "ï"),
+    ("Ã±", "ñ"),
+    ("Ã³", "ó"),
+    ("Ã´", "ô"),
+    ("Ã¶", "ö"),
+    ("Ã¹", "ù"),
+    ("Ãº", "ú"),
+    ("Ã»", "û"),
+    ("Ã¼", "ü"),
+    ("ÃŸ", "ß"),
+    ("Ã‰", "É"),
+    ("Ã–", "Ö"),
+    ("Ãœ", "Ü"),
+    ("Ã„", "Ä"),
+];
+
+// Editors on Windows like to start UTF-8 files with one of these
+const UTF8_BOM: &[u8] = b"\xEF\xBB\xBF";
+
+pub(crate) fn has_utf8_bom(bytes: &[u8]) -> bool {
+    bytes.starts_with(UTF8_BOM)
+}
+
+// Drop a leading byte order mark from decoded text
+pub(crate) fn without_bom(text: String) -> String {
+    match text.strip_prefix('\u{feff}') {
+        Some(rest) => rest.to_string(),
+        None => text,
+    }
+}
+
+// Decode a note's bytes, guessing the encoding when they aren't valid UTF-8.
+// Returns the text and, if it had to be transcoded, the name of the encoding
+pub(crate) fn decode(bytes: Vec<u8>) -> (String, Option<&'static str>) {
+    match String::from_utf8(bytes) {
+        Ok(text) => (text, None),
+        Err(e) => {
+            let bytes = e.into_bytes();
+            let mut detector = EncodingDetector::new(Iso2022JpDetection::Deny);
+            detector.feed(&bytes, true);
+            let encoding = detector.guess(None, Utf8Detection::Deny);
+            let (text, _, _) = encoding.decode(&bytes);
+            (text.into_owned(), Some(encoding.name()))
+        }
+    }
+}
+
+// Rewrite a non-UTF-8 note as UTF-8; returns the encoding it was converted from
+#[tauri::command]
+pub async fn transcode_to_utf8(path: String) -> Result<Option<String>, String> {
+    let dir = get_margherita_dir()?;
+    let file_path = resolve_in_vault(&path)?;
+    let bytes = fs::read(&file_path).map_err(|e| format!("Failed to read file: {}", e))?;
+
+    let (content, encoding) = decode(bytes);
+    let Some(encoding) = encoding else {
+        return Ok(None);
+    };
+
+    ensure_unlocked(&relative_path(&dir, &file_path))?;
+    write_atomic(&file_path, &content)?;
+    println!("Transcoded {} from {} to UTF-8", path, encoding);
+    Ok(Some(encoding.to_string()))
+}
+
+// Repair text that went through a UTF-8/Windows-1252 mix-up, e.g. "donâ€™t" -> "don’t"
+#[tauri::command]
+pub async fn fix_encoding_artifacts(content: String) -> Result<String, String> {
+    let mut fixed = content;
+    for (broken, correct) in MOJIBAKE {
+        if fixed.contains(broken) {
+            fixed = fixed.replace(broken, correct);
+        }
+    }
+    Ok(fixed)
+}
+
+#[tauri::command]
+pub async fn has_bom(path: String) -> Result<bool, String> {
+    let file_path = resolve_in_vault(&path)?;
+    let bytes = fs::read(&file_path).map_err(|e| format!("Failed to read file: {}", e))?;
+    Ok(has_utf8_bom(&bytes))
+}
+
+// Rewrite a note without its UTF-8 byte order mark; returns whether it had one
+#[tauri::command]
+pub async fn strip_bom(path: String) -> Result<bool, String> {
+    let dir = get_margherita_dir()?;
+    let file_path = resolve_in_vault(&path)?;
+    let bytes = fs::read(&file_path).map_err(|e| format!("Failed to read file: {}", e))?;
+    if !has_utf8_bom(&bytes) {
+        return Ok(false);
+    }
+
+    let content = String::from_utf8(bytes[UTF8_BOM.len()..].to_vec())
+        .map_err(|_| format!("{} isn't valid UTF-8 after its byte order mark", path))?;
+    ensure_unlocked(&relative_path(&dir, &file_path))?;
+    write_atomic(&file_path, &content)?;
+    println!("Removed the byte order mark from {}", path);
+    Ok(true)
+}
@@ -0,0 +1,21 @@
+use serde::Serialize;
+
+// Errors from file commands, tagged so the frontend can tell them apart
+#[derive(Debug, Serialize)]
+#[serde(tag = "kind", content = "message")]
+pub enum FileError {
+    AlreadyExists(String),
+    NotFound(String),
+    PermissionDenied(String),
+    OutsideVault(String),
+    Locked(String),
+    ReadOnlyMode(String),
+    TooLarge(String),
+    Io(String),
+}
+
+impl From<String> for FileError {
+    fn from(message: String) -> Self {
+        FileError::Io(message)
+    }
+}
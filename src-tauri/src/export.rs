@@ -0,0 +1,360 @@
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use serde::Deserialize;
+use std::collections::HashSet;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use tauri::State;
+
+use crate::attachments::mime_type;
+use crate::config::{read_config, update_config};
+use crate::frontmatter::{notes_matching, split_frontmatter};
+use crate::get_margherita_dir;
+use crate::links::{
+    apply_edits, markdown_links, percent_encode_path, resolve_link, split_fragment, wikilinks,
+};
+use crate::markdown::{escape_html, render_html, render_html_with_images};
+use crate::settings::SettingsState;
+use crate::title::note_title;
+use crate::vault::{collect_notes, is_note, parent_of, relative_from, resolve_in_vault};
+use crate::wikilink::WikiResolver;
+
+// Stylesheets bundled into the binary for exports and themed previews
+const THEMES: &[(&str, &str)] = &[
+    ("light", include_str!("../themes/light.css")),
+    ("dark", include_str!("../themes/dark.css")),
+    ("sepia", include_str!("../themes/sepia.css")),
+];
+
+const DEFAULT_THEME: &str = "light";
+
+// Anything bigger is almost certainly not a hand-written stylesheet
+const MAX_EXPORT_CSS_BYTES: usize = 256 * 1024;
+
+// Images bigger than this keep their relative path instead of bloating the export
+const MAX_INLINE_IMAGE_BYTES: u64 = 10 * 1024 * 1024;
+
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum BundleFormat {
+    Html,
+    Markdown,
+}
+
+// Start every note of a printed folder on a new page
+const PRINT_CSS: &str = ".page-break { break-after: page; page-break-after: always; }\n";
+
+pub(crate) fn theme_css(theme: &str) -> Result<&'static str, String> {
+    THEMES
+        .iter()
+        .find(|(name, _)| *name == theme)
+        .map(|(_, css)| *css)
+        .ok_or_else(|| format!("Unknown theme: {}", theme))
+}
+
+// The theme's stylesheet combined with the user's export CSS, if they set one
+fn export_css(theme: &str) -> Result<String, String> {
+    let theme_css = theme_css(theme)?;
+    let config = read_config()?;
+    Ok(if config.export_css.trim().is_empty() {
+        theme_css.to_string()
+    } else if config.export_css_replaces_theme {
+        config.export_css
+    } else {
+        format!("{}\n{}", theme_css, config.export_css)
+    })
+}
+
+// Wrap rendered HTML in a complete page with its stylesheet inlined
+pub(crate) fn standalone_document(title: &str, body: &str, css: &str) -> String {
+    format!(
+        "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n<meta name=\"viewport\" content=\"width=device-width, initial-scale=1\">\n<title>{}</title>\n<style>\n{}</style>\n</head>\n<body>\n{}</body>\n</html>\n",
+        escape_html(title),
+        css,
+        body
+    )
+}
+
+// A local image referenced from `note` as a data: URL, if it's small enough to embed.
+// Remote images and anything that can't be read are left alone
+fn inline_image(note: &str, dest: &str) -> Option<String> {
+    let relative = resolve_link(note, dest)?;
+    let path = resolve_in_vault(&relative).ok()?;
+    let size = fs::metadata(&path).ok()?.len();
+    if size > MAX_INLINE_IMAGE_BYTES {
+        println!("Not inlining {} ({} bytes)", relative, size);
+        return None;
+    }
+    let bytes = fs::read(&path).ok()?;
+    let mime = mime_type(&path, &bytes).filter(|m| m.starts_with("image/"))?;
+    Some(format!("data:{};base64,{}", mime, STANDARD.encode(&bytes)))
+}
+
+// `inline_images_from` is the note's path, when its images should be embedded
+fn themed_document(
+    content: &str,
+    fallback_title: &str,
+    theme: Option<String>,
+    inline_images_from: Option<&str>,
+    settings: &SettingsState,
+) -> Result<String, String> {
+    let css = export_css(theme.as_deref().unwrap_or(DEFAULT_THEME))?;
+    let (_, body) = split_frontmatter(content);
+    let options = settings.get()?.parse_options;
+    let body = match inline_images_from {
+        Some(note) => render_html_with_images(body, &options, |dest| inline_image(note, dest)),
+        None => render_html(body, &options),
+    };
+    let title = note_title(content).unwrap_or_else(|| fallback_title.to_string());
+    Ok(standalone_document(&title, &body, &css))
+}
+
+#[tauri::command]
+pub async fn list_themes() -> Result<Vec<String>, String> {
+    Ok(THEMES.iter().map(|(name, _)| name.to_string()).collect())
+}
+
+// Export a note as a self-contained HTML page in the chosen theme. With `inline_images`,
+// local images are embedded as data: URLs so the page still works once moved
+#[tauri::command]
+pub async fn export_html(
+    path: String,
+    theme: Option<String>,
+    inline_images: Option<bool>,
+    settings: State<'_, SettingsState>,
+) -> Result<String, String> {
+    let file_path = resolve_in_vault(&path)?;
+    let content =
+        fs::read_to_string(&file_path).map_err(|e| format!("Failed to read file: {}", e))?;
+    let name = Path::new(&path)
+        .file_stem()
+        .map(|s| s.to_string_lossy().into_owned())
+        .unwrap_or_default();
+
+    let inline_images_from = inline_images.unwrap_or(false).then_some(path.as_str());
+    themed_document(&content, &name, theme, inline_images_from, &settings)
+}
+
+// Render unsaved editor content the same way export_html would
+#[tauri::command]
+pub async fn preview_with_theme(
+    content: String,
+    theme: String,
+    settings: State<'_, SettingsState>,
+) -> Result<String, String> {
+    themed_document(&content, "Preview", Some(theme), None, &settings)
+}
+
+// Render every note in a folder into one print-ready page, a note per page
+#[tauri::command]
+pub async fn render_folder(
+    folder: String,
+    settings: State<'_, SettingsState>,
+) -> Result<String, String> {
+    let folder_path = resolve_in_vault(&folder)?;
+    if !folder_path.is_dir() {
+        return Err(format!("Folder not found: {}", folder));
+    }
+
+    let options = settings.get()?.parse_options;
+    let mut pages = Vec::new();
+    for path in collect_notes(&folder_path)? {
+        let content =
+            fs::read_to_string(&path).map_err(|e| format!("Failed to read file: {}", e))?;
+        let (_, body) = split_frontmatter(&content);
+        pages.push(format!(
+            "<article>\n{}</article>\n",
+            render_html(body, &options)
+        ));
+    }
+
+    let body = pages.join("<div class=\"page-break\"></div>\n");
+    let css = format!("{}{}", export_css(DEFAULT_THEME)?, PRINT_CSS);
+    let title = folder
+        .trim_end_matches('/')
+        .rsplit('/')
+        .next()
+        .unwrap_or(&folder);
+    println!(
+        "Rendered {} notes from {} for printing",
+        pages.len(),
+        folder
+    );
+    Ok(standalone_document(title, &body, &css))
+}
+
+// Save a stylesheet to embed in exports; an empty one goes back to the plain theme
+#[tauri::command]
+pub async fn set_export_css(css: String, replace_theme: Option<bool>) -> Result<(), String> {
+    if css.len() > MAX_EXPORT_CSS_BYTES {
+        return Err(format!(
+            "Export CSS is too large ({} bytes, at most {})",
+            css.len(),
+            MAX_EXPORT_CSS_BYTES
+        ));
+    }
+    // It's inlined into a <style> element, which it must not be able to close
+    if css.to_ascii_lowercase().contains("</style") {
+        return Err("Export CSS can't contain </style>".to_string());
+    }
+    update_config(|config| {
+        config.export_css = css;
+        config.export_css_replaces_theme = replace_theme.unwrap_or(false);
+    })
+}
+
+#[tauri::command]
+pub async fn get_export_css() -> Result<String, String> {
+    Ok(read_config()?.export_css)
+}
+
+// Where a note lands in a bundle, keeping its place in the vault's layout
+fn bundle_path(note: &str, format: BundleFormat) -> String {
+    match format {
+        BundleFormat::Html => Path::new(note)
+            .with_extension("html")
+            .to_string_lossy()
+            .into_owned(),
+        BundleFormat::Markdown => note.to_string(),
+    }
+}
+
+// Point a note's links at the bundled copies of the notes in `bundle`, and turn links
+// to any other note into plain text. Also returns the attachments the note links to
+fn bundle_links(
+    content: &str,
+    note: &str,
+    resolver: &WikiResolver,
+    bundle: &HashSet<&str>,
+    format: BundleFormat,
+) -> (String, Vec<String>) {
+    let mut edits = Vec::new();
+    let mut attachments = Vec::new();
+    let link_to = |target: &str| percent_encode_path(&relative_from(parent_of(note), target));
+
+    for link in wikilinks(content) {
+        let Ok(target) = resolver.resolve(&link.target) else {
+            continue;
+        };
+        // The range only covers the target, so widen it to the brackets and any alias
+        let Some(open) = content[..link.range.start].rfind("[[") else {
+            continue;
+        };
+        let Some(close) = content[link.range.end..].find("]]") else {
+            continue;
+        };
+        let inner = &content[open + 2..link.range.end + close];
+        let text = inner
+            .split_once('|')
+            .map_or(link.target.as_str(), |(_, a)| a.trim());
+        let open = if link.embed { open - 1 } else { open };
+        let range = open..link.range.end + close + 2;
+
+        if bundle.contains(target) {
+            let dest = link_to(&bundle_path(target, format));
+            edits.push((range, format!("[{}]({})", text, dest)));
+        } else {
+            edits.push((range, text.to_string()));
+        }
+    }
+
+    for link in markdown_links(content) {
+        let Some(target) = resolve_link(note, &link.dest) else {
+            continue;
+        };
+        if !is_note(Path::new(&target)) {
+            attachments.push(target);
+        } else if !bundle.contains(target.as_str()) {
+            edits.push((link.link, content[link.text].to_string()));
+        } else if format == BundleFormat::Html {
+            let (_, fragment) = split_fragment(&link.dest);
+            let dest = link_to(&bundle_path(&target, format));
+            edits.push((link.range, format!("{}{}", dest, fragment)));
+        }
+    }
+
+    (apply_edits(content, edits), attachments)
+}
+
+fn write_bundle_file(path: &Path, contents: &[u8]) -> Result<(), String> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent).map_err(|e| format!("Failed to create directory: {}", e))?;
+    }
+    fs::write(path, contents).map_err(|e| format!("Failed to write file: {}", e))
+}
+
+// Export every note tagged `tag` into `out_dir`, as themed HTML pages or as markdown,
+// along with the attachments they link to. Links between exported notes are kept and
+// links to notes left out become plain text. Returns the paths written
+#[tauri::command]
+pub async fn export_by_tag(
+    tag: String,
+    out_dir: String,
+    format: Option<BundleFormat>,
+    settings: State<'_, SettingsState>,
+) -> Result<Vec<String>, String> {
+    let dir = get_margherita_dir()?;
+    let format = format.unwrap_or(BundleFormat::Html);
+    let tag = tag.trim().trim_start_matches('#');
+    let out = PathBuf::from(&out_dir);
+
+    fs::create_dir_all(&out).map_err(|e| format!("Failed to create directory: {}", e))?;
+    let real_out = fs::canonicalize(&out).map_err(|e| format!("Failed to resolve path: {}", e))?;
+    if fs::canonicalize(&dir).is_ok_and(|vault| real_out.starts_with(vault)) {
+        return Err("Can't export into the vault itself".to_string());
+    }
+
+    let tagged = notes_matching(&dir, "tags", tag)?;
+    if tagged.is_empty() {
+        return Err(format!("No notes tagged {}", tag));
+    }
+    let resolver = WikiResolver::build(&dir)?;
+    let bundle: HashSet<&str> = tagged.iter().map(|n| n.as_str()).collect();
+
+    let mut exported = Vec::new();
+    let mut copied = HashSet::new();
+    for note in &tagged {
+        let content = fs::read_to_string(dir.join(note))
+            .map_err(|e| format!("Failed to read {}: {}", note, e))?;
+        let (content, attachments) = bundle_links(&content, note, &resolver, &bundle, format);
+
+        let contents = match format {
+            BundleFormat::Html => {
+                let name = Path::new(note)
+                    .file_stem()
+                    .map(|s| s.to_string_lossy().into_owned())
+                    .unwrap_or_default();
+                themed_document(&content, &name, None, None, &settings)?
+            }
+            BundleFormat::Markdown => content,
+        };
+        let target = out.join(bundle_path(note, format));
+        write_bundle_file(&target, contents.as_bytes())?;
+        exported.push(target.to_string_lossy().into_owned());
+
+        for attachment in attachments {
+            if !copied.insert(attachment.clone()) {
+                continue;
+            }
+            let Ok(source) = resolve_in_vault(&attachment) else {
+                continue;
+            };
+            let Ok(bytes) = fs::read(&source) else {
+                println!("Skipping missing attachment {}", attachment);
+                continue;
+            };
+            let target = out.join(&attachment);
+            write_bundle_file(&target, &bytes)?;
+            exported.push(target.to_string_lossy().into_owned());
+        }
+    }
+
+    println!(
+        "Exported {} notes tagged {} to {:?}",
+        tagged.len(),
+        tag,
+        out
+    );
+    Ok(exported)
+}
@@ -0,0 +1,247 @@
+use pulldown_cmark::{CodeBlockKind, Event, Options, Parser, Tag, TagEnd};
+use serde::Serialize;
+use std::fs;
+
+use crate::frontmatter::split_frontmatter;
+use crate::get_margherita_dir;
+use crate::markdown::LineIndex;
+use crate::vault::{active_notes, relative_path};
+
+#[derive(Debug, Serialize)]
+pub struct CodeBlock {
+    language: Option<String>,
+    line: usize,
+    code: String,
+}
+
+// Every fenced or indented code block in a note; inline code is left out
+#[tauri::command]
+pub async fn extract_code_blocks(content: String) -> Result<Vec<CodeBlock>, String> {
+    let (_, body) = split_frontmatter(&content);
+    let body_offset = content.len() - body.len();
+    let lines = LineIndex::new(&content);
+
+    let mut blocks = Vec::new();
+    let mut current: Option<CodeBlock> = None;
+
+    for (event, range) in Parser::new_ext(body, Options::all()).into_offset_iter() {
+        match event {
+            Event::Start(Tag::CodeBlock(kind)) => {
+                let language = match kind {
+                    CodeBlockKind::Fenced(info) => {
+                        info.split_whitespace().next().map(|lang| lang.to_string())
+                    }
+                    CodeBlockKind::Indented => None,
+                };
+                current = Some(CodeBlock {
+                    language,
+                    line: lines.line(body_offset + range.start),
+                    code: String::new(),
+                });
+            }
+            Event::Text(text) => {
+                if let Some(block) = current.as_mut() {
+                    block.code.push_str(&text);
+                }
+            }
+            Event::End(TagEnd::CodeBlock) => blocks.extend(current.take()),
+            _ => {}
+        }
+    }
+
+    Ok(blocks)
+}
+
+// A table's cell text in document order. `headers` is empty when the header row is,
+// so the first row isn't mistaken for data labels
+#[derive(Debug, Serialize)]
+pub struct TableData {
+    line: usize,
+    headers: Vec<String>,
+    rows: Vec<Vec<String>>,
+}
+
+// Every table in a note, as plain cell text; formatting inside cells is dropped
+#[tauri::command]
+pub async fn extract_tables(content: String) -> Result<Vec<TableData>, String> {
+    let (_, body) = split_frontmatter(&content);
+    let body_offset = content.len() - body.len();
+    let lines = LineIndex::new(&content);
+
+    let mut tables = Vec::new();
+    let mut current: Option<TableData> = None;
+    let mut row: Vec<String> = Vec::new();
+    let mut cell: Option<String> = None;
+
+    for (event, range) in Parser::new_ext(body, Options::all()).into_offset_iter() {
+        match event {
+            Event::Start(Tag::Table(_)) => {
+                current = Some(TableData {
+                    line: lines.line(body_offset + range.start),
+                    headers: Vec::new(),
+                    rows: Vec::new(),
+                });
+            }
+            Event::Start(Tag::TableCell) => cell = Some(String::new()),
+            Event::Text(text) | Event::Code(text) | Event::InlineMath(text) => {
+                if let Some(cell) = cell.as_mut() {
+                    cell.push_str(&text);
+                }
+            }
+            Event::SoftBreak | Event::HardBreak => {
+                if let Some(cell) = cell.as_mut() {
+                    cell.push(' ');
+                }
+            }
+            Event::End(TagEnd::TableCell) => {
+                row.extend(cell.take().map(|c| c.trim().to_string()));
+            }
+            Event::End(TagEnd::TableHead) => {
+                let headers = std::mem::take(&mut row);
+                if let Some(table) = current.as_mut() {
+                    if headers.iter().any(|h| !h.is_empty()) {
+                        table.headers = headers;
+                    }
+                }
+            }
+            Event::End(TagEnd::TableRow) => {
+                let cells = std::mem::take(&mut row);
+                if let Some(table) = current.as_mut() {
+                    table.rows.push(cells);
+                }
+            }
+            Event::End(TagEnd::Table) => tables.extend(current.take()),
+            _ => {}
+        }
+    }
+
+    Ok(tables)
+}
+
+#[derive(Debug, Serialize)]
+pub struct Task {
+    text: String,
+    pub(crate) checked: bool,
+    line: usize,
+    // 0 for top-level items, 1 for items nested one list deep, and so on
+    depth: usize,
+}
+
+// A list item being walked: where it starts, its task (if it has a checkbox),
+// and whether its own text is still being read rather than a nested list's
+struct OpenItem {
+    start: usize,
+    task: Option<usize>,
+    collecting: bool,
+}
+
+// Every `- [ ]` / `- [x]` item in a note, in document order
+pub(crate) fn tasks(content: &str) -> Vec<Task> {
+    let (_, body) = split_frontmatter(content);
+    let body_offset = content.len() - body.len();
+    let lines = LineIndex::new(content);
+
+    let mut tasks: Vec<Task> = Vec::new();
+    let mut items: Vec<OpenItem> = Vec::new();
+    let mut list_depth = 0;
+
+    for (event, range) in Parser::new_ext(body, Options::all()).into_offset_iter() {
+        match event {
+            Event::Start(Tag::List(_)) => {
+                if let Some(item) = items.last_mut() {
+                    item.collecting = false;
+                }
+                list_depth += 1;
+            }
+            Event::End(TagEnd::List(_)) => list_depth -= 1,
+            Event::Start(Tag::Item) => items.push(OpenItem {
+                start: body_offset + range.start,
+                task: None,
+                collecting: true,
+            }),
+            Event::TaskListMarker(checked) => {
+                if let Some(item) = items.last_mut() {
+                    item.task = Some(tasks.len());
+                    tasks.push(Task {
+                        text: String::new(),
+                        checked,
+                        line: lines.line(item.start),
+                        depth: list_depth - 1,
+                    });
+                }
+            }
+            Event::Text(text) | Event::Code(text) => {
+                if let Some(OpenItem {
+                    task: Some(index),
+                    collecting: true,
+                    ..
+                }) = items.last()
+                {
+                    tasks[*index].text.push_str(&text);
+                }
+            }
+            Event::SoftBreak | Event::HardBreak => {
+                if let Some(OpenItem {
+                    task: Some(index),
+                    collecting: true,
+                    ..
+                }) = items.last()
+                {
+                    tasks[*index].text.push(' ');
+                }
+            }
+            Event::End(TagEnd::Item) => {
+                if let Some(index) = items.pop().and_then(|item| item.task) {
+                    tasks[index].text = tasks[index].text.trim().to_string();
+                }
+            }
+            _ => {}
+        }
+    }
+
+    tasks
+}
+
+#[tauri::command]
+pub async fn extract_tasks(content: String) -> Result<Vec<Task>, String> {
+    Ok(tasks(&content))
+}
+
+// An unchecked task somewhere in the vault
+#[derive(Debug, Serialize)]
+pub struct VaultTask {
+    note: String,
+    line: usize,
+    text: String,
+}
+
+// Upper bound on all_open_tasks, so a huge vault can't flood the frontend
+const MAX_VAULT_TASKS: usize = 1000;
+
+// Unchecked tasks from every active note, grouped by note in name order
+#[tauri::command]
+pub async fn all_open_tasks() -> Result<Vec<VaultTask>, String> {
+    let dir = get_margherita_dir()?;
+    let mut open = Vec::new();
+
+    // Notes are read one at a time and dropped as soon as their tasks are pulled out
+    'notes: for path in active_notes(&dir)? {
+        let Ok(content) = fs::read_to_string(&path) else {
+            continue;
+        };
+        let note = relative_path(&dir, &path);
+        for task in tasks(&content).into_iter().filter(|t| !t.checked) {
+            if open.len() == MAX_VAULT_TASKS {
+                println!("Stopped collecting tasks at {}", MAX_VAULT_TASKS);
+                break 'notes;
+            }
+            open.push(VaultTask {
+                note: note.clone(),
+                line: task.line,
+                text: task.text,
+            });
+        }
+    }
+
+    Ok(open)
+}
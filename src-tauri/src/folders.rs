@@ -0,0 +1,131 @@
+use std::collections::{BTreeSet, HashSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::archive::ARCHIVE_DIR;
+use crate::get_margherita_dir;
+use crate::lock::locked_notes;
+use crate::mode::ensure_editable;
+use crate::trash::{move_to_trash, TRASH_DIR};
+use crate::util::now_millis;
+use crate::vault::{collect_notes, is_note, relative_path, resolve_for_write, resolve_in_vault};
+use crate::FileItem;
+
+// Folders (first) and notes under `folder`, each folder with its own children.
+// Hidden folders are skipped, and a symlinked folder that was already listed is left empty
+fn tree(
+    dir: &Path,
+    folder: &Path,
+    locked: &BTreeSet<String>,
+    visited: &mut HashSet<PathBuf>,
+) -> Result<Vec<FileItem>, String> {
+    let real = fs::canonicalize(folder).unwrap_or_else(|_| folder.to_path_buf());
+    if !visited.insert(real) {
+        println!("Skipping already visited folder {:?}", folder);
+        return Ok(Vec::new());
+    }
+
+    let entries = fs::read_dir(folder).map_err(|e| format!("Failed to read directory: {}", e))?;
+    let mut folders = Vec::new();
+    let mut notes = Vec::new();
+
+    for entry in entries.flatten() {
+        let path = entry.path();
+        let name = entry.file_name().to_string_lossy().into_owned();
+        let is_symlink = entry.file_type().is_ok_and(|t| t.is_symlink());
+        let relative = relative_path(dir, &path);
+
+        if path.is_dir() {
+            if name.starts_with('.') {
+                continue;
+            }
+            folders.push(FileItem {
+                children: Some(tree(dir, &path, locked, visited)?),
+                locked: false,
+                name,
+                path: relative,
+                is_dir: true,
+                is_symlink,
+            });
+        } else if is_note(&path) {
+            notes.push(FileItem {
+                locked: locked.contains(&relative),
+                name,
+                path: relative,
+                is_dir: false,
+                is_symlink,
+                children: None,
+            });
+        }
+    }
+
+    folders.sort_by_key(|f| f.name.to_lowercase());
+    notes.sort_by_key(|n| n.name.to_lowercase());
+    folders.extend(notes);
+    Ok(folders)
+}
+
+// The whole vault as a tree of folders and notes, optionally with the archive
+#[tauri::command]
+pub async fn list_tree(include_archive: Option<bool>) -> Result<Vec<FileItem>, String> {
+    let dir = get_margherita_dir()?;
+    if !dir.exists() {
+        return Ok(Vec::new());
+    }
+
+    let locked = locked_notes()?;
+    let mut items = tree(&dir, &dir, &locked, &mut HashSet::new())?;
+    if !include_archive.unwrap_or(false) {
+        items.retain(|item| !(item.is_dir && item.path == ARCHIVE_DIR));
+    }
+    Ok(items)
+}
+
+// Create a folder (and any missing parents) inside the vault
+#[tauri::command]
+pub async fn create_folder(path: String) -> Result<String, String> {
+    ensure_editable()?;
+    let dir = get_margherita_dir()?;
+    let folder = resolve_for_write(&path)?;
+    let relative = relative_path(&dir, &folder);
+    if relative.is_empty() {
+        return Err("Can't create the margherita directory itself".to_string());
+    }
+    if folder.exists() {
+        return Err(format!("{} already exists", relative));
+    }
+
+    fs::create_dir_all(&folder).map_err(|e| format!("Failed to create folder: {}", e))?;
+    println!("Created folder {:?}", folder);
+    Ok(relative)
+}
+
+// Move a folder and everything in it to the trash; returns how many notes went with it.
+// Refused while any note inside is locked
+#[tauri::command]
+pub async fn delete_folder(path: String) -> Result<usize, String> {
+    ensure_editable()?;
+    let dir = get_margherita_dir()?;
+    let folder = resolve_in_vault(&path)?;
+    let relative = relative_path(&dir, &folder);
+    if relative.is_empty() || relative.split('/').next() == Some(TRASH_DIR) {
+        return Err(format!("Can't delete {}", path));
+    }
+    if !folder.is_dir() {
+        return Err(format!("Folder not found: {}", path));
+    }
+
+    let notes = collect_notes(&folder)?;
+    let locked = locked_notes()?;
+    if let Some(note) = notes
+        .iter()
+        .map(|p| relative_path(&dir, p))
+        .find(|n| locked.contains(n))
+    {
+        return Err(format!("Note is locked: {}", note));
+    }
+
+    move_to_trash(&dir, &folder, now_millis())?;
+    println!("Deleted folder {} with {} notes", relative, notes.len());
+    Ok(notes.len())
+}
@@ -0,0 +1,129 @@
+use serde::Serialize;
+
+#[derive(Debug, Clone, Copy, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum FoldKind {
+    Frontmatter,
+    Heading,
+    Code,
+}
+
+// A foldable region, as 1-based inclusive line numbers
+#[derive(Debug, Serialize)]
+pub struct FoldRange {
+    kind: FoldKind,
+    start_line: usize,
+    end_line: usize,
+}
+
+// The level of an ATX heading line, if it is one
+fn heading_level(line: &str) -> Option<usize> {
+    let trimmed = line.trim_start_matches(' ');
+    if line.len() - trimmed.len() > 3 {
+        return None;
+    }
+    let level = trimmed.bytes().take_while(|&b| b == b'#').count();
+    let rest = &trimmed[level..];
+    ((1..=6).contains(&level) && (rest.is_empty() || rest.starts_with([' ', '\t'])))
+        .then_some(level)
+}
+
+// The fence character and length if `line` opens or closes a fenced code block
+fn fence(line: &str) -> Option<(char, usize)> {
+    let trimmed = line.trim_start_matches(' ');
+    if line.len() - trimmed.len() > 3 {
+        return None;
+    }
+    let ch = trimmed.chars().next().filter(|c| *c == '`' || *c == '~')?;
+    let len = trimmed.chars().take_while(|c| *c == ch).count();
+    (len >= 3).then_some((ch, len))
+}
+
+fn push(ranges: &mut Vec<FoldRange>, kind: FoldKind, start_line: usize, end_line: usize) {
+    if end_line > start_line {
+        ranges.push(FoldRange {
+            kind,
+            start_line,
+            end_line,
+        });
+    }
+}
+
+// Foldable regions in one pass over the lines: frontmatter, fenced code, and heading
+// sections, which run until the next heading of the same or a higher level
+pub(crate) fn fold_ranges(content: &str) -> Vec<FoldRange> {
+    let lines: Vec<&str> = content.lines().collect();
+    let mut ranges = Vec::new();
+    // Open heading sections as (level, start line)
+    let mut sections: Vec<(usize, usize)> = Vec::new();
+    // The last line with any content, so sections don't swallow trailing blank lines
+    let mut last_text = 0;
+    let mut open_fence: Option<(char, usize, usize)> = None;
+    let mut start = 0;
+
+    if lines.first().map(|l| l.trim_end()) == Some("---") {
+        if let Some(end) = lines[1..]
+            .iter()
+            .position(|l| matches!(l.trim_end(), "---" | "..."))
+        {
+            push(&mut ranges, FoldKind::Frontmatter, 1, end + 2);
+            start = end + 2;
+            last_text = start;
+        }
+    }
+
+    for (i, line) in lines.iter().enumerate().skip(start) {
+        let number = i + 1;
+
+        if let Some((ch, len, opened)) = open_fence {
+            if fence(line)
+                .is_some_and(|(c, l)| c == ch && l >= len && line.trim().chars().all(|x| x == ch))
+            {
+                push(&mut ranges, FoldKind::Code, opened, number);
+                open_fence = None;
+            }
+            last_text = number;
+            continue;
+        }
+
+        if let Some((ch, len)) = fence(line) {
+            // A backtick fence's info string can't contain backticks
+            if ch == '~' || !line.trim_start()[len..].contains('`') {
+                open_fence = Some((ch, len, number));
+                last_text = number;
+                continue;
+            }
+        }
+
+        if let Some(level) = heading_level(line) {
+            while let Some(&(open_level, opened)) = sections.last() {
+                if open_level < level {
+                    break;
+                }
+                push(&mut ranges, FoldKind::Heading, opened, last_text);
+                sections.pop();
+            }
+            sections.push((level, number));
+        }
+
+        if !line.trim().is_empty() {
+            last_text = number;
+        }
+    }
+
+    // An unclosed fence runs to the end of the note, like it renders
+    if let Some((_, _, opened)) = open_fence {
+        push(&mut ranges, FoldKind::Code, opened, lines.len());
+    }
+    while let Some((_, opened)) = sections.pop() {
+        push(&mut ranges, FoldKind::Heading, opened, last_text);
+    }
+
+    ranges.sort_by_key(|r| (r.start_line, std::cmp::Reverse(r.end_line)));
+    ranges
+}
+
+#[tauri::command]
+pub async fn folding_ranges(content: String) -> Result<Vec<FoldRange>, String> {
+    Ok(fold_ranges(&content))
+}
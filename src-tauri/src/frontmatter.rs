@@ -0,0 +1,510 @@
+use serde::Serialize;
+use serde_json::{Map, Value};
+use std::collections::BTreeSet;
+use std::fs::{self, File};
+use std::io::{BufRead, BufReader};
+use std::ops::Range;
+use std::path::Path;
+
+use crate::get_margherita_dir;
+use crate::lock::ensure_unlocked;
+use crate::vault::{active_notes, relative_path, resolve_in_vault, write_atomic};
+
+// A folder's shared frontmatter, inherited by every note below it
+pub(crate) const DEFAULTS_FILE: &str = "_defaults.md";
+
+// A closing fence further down than this means the opening `---` was a thematic break
+const MAX_FRONTMATTER_LINES: usize = 200;
+
+#[derive(Debug, Serialize)]
+pub struct FrontmatterInfo {
+    found: bool,
+    // Byte range of the whole block, fences included
+    range: Option<Range<usize>>,
+}
+
+// Where a note's frontmatter stops being valid YAML, as 1-based positions in the note
+#[derive(Debug, Serialize)]
+pub struct FrontmatterError {
+    message: String,
+    line: usize,
+    column: usize,
+}
+
+// Frontmatter has to be a YAML mapping (or empty); anything else is body text between rules
+fn is_frontmatter_yaml(yaml: &str) -> bool {
+    yaml.trim().is_empty() || serde_yaml::from_str::<Value>(yaml).is_ok_and(|v| v.is_object())
+}
+
+// The YAML between the fences and the byte offset where the body starts
+fn frontmatter_bounds(content: &str) -> Option<(Range<usize>, usize)> {
+    fenced_block(content).filter(|(yaml, _)| is_frontmatter_yaml(&content[yaml.clone()]))
+}
+
+// Like frontmatter_bounds, but only looking at the fences, not at what's between them
+fn fenced_block(content: &str) -> Option<(Range<usize>, usize)> {
+    // The opener may end in CRLF or carry trailing spaces, like the closing fence
+    let opener = content.split_inclusive('\n').next()?;
+    if !opener.ends_with('\n') || opener.trim_end() != "---" {
+        return None;
+    }
+    let start = opener.len();
+    let rest = &content[start..];
+
+    let mut offset = 0;
+    for line in rest.split_inclusive('\n').take(MAX_FRONTMATTER_LINES) {
+        let trimmed = line.trim_end_matches(['\r', '\n']);
+        if trimmed == "---" || trimmed == "..." {
+            return Some((start..start + offset, start + offset + line.len()));
+        }
+        offset += line.len();
+    }
+
+    // No closing fence nearby, so this isn't frontmatter after all
+    None
+}
+
+// Split a note into its YAML frontmatter block (without the fences) and body
+pub(crate) fn split_frontmatter(content: &str) -> (Option<&str>, &str) {
+    match frontmatter_bounds(content) {
+        Some((yaml, body)) => (Some(&content[yaml]), &content[body..]),
+        None => (None, content),
+    }
+}
+
+// Parse a note's frontmatter into a JSON value, if it has a valid mapping
+pub(crate) fn parse_frontmatter(content: &str) -> Option<Value> {
+    let (yaml, _) = split_frontmatter(content);
+    let value: Value = serde_yaml::from_str(yaml?).ok()?;
+    value.is_object().then_some(value)
+}
+
+// Read a string field from parsed frontmatter, ignoring blank values
+pub(crate) fn string_field(frontmatter: &Value, field: &str) -> Option<String> {
+    frontmatter
+        .get(field)
+        .and_then(|v| v.as_str())
+        .map(|s| s.trim().to_string())
+        .filter(|s| !s.is_empty())
+}
+
+// Scalars compare as strings; lists match when any item does
+pub(crate) fn value_matches(field: &Value, value: &str) -> bool {
+    match field {
+        Value::String(s) => s.trim() == value,
+        Value::Number(n) => n.to_string() == value,
+        Value::Bool(b) => b.to_string() == value,
+        Value::Array(items) => items.iter().any(|item| value_matches(item, value)),
+        _ => false,
+    }
+}
+
+// Set a top-level scalar field in a note's frontmatter, editing the YAML as text
+// so the other keys keep their formatting. Adds a frontmatter block if needed, but
+// leaves the note alone when its fenced block isn't valid YAML, rather than stacking
+// a second block on top of it
+pub(crate) fn set_frontmatter_field(content: &str, field: &str, value: &str) -> String {
+    let newline = if content.contains("\r\n") {
+        "\r\n"
+    } else {
+        "\n"
+    };
+    let entry = format!("{}: {}", field, value);
+
+    let (yaml, body) = split_frontmatter(content);
+    let Some(yaml) = yaml else {
+        if fenced_block(content).is_some() {
+            println!("Not setting {}: the frontmatter isn't valid YAML", field);
+            return content.to_string();
+        }
+        return format!("---{nl}{}{nl}---{nl}{}", entry, content, nl = newline);
+    };
+
+    let prefix = format!("{}:", field);
+    let mut lines: Vec<String> = Vec::new();
+    let mut replaced = false;
+    for line in yaml.lines() {
+        if !replaced && line.starts_with(&prefix) {
+            lines.push(entry.clone());
+            replaced = true;
+        } else {
+            lines.push(line.to_string());
+        }
+    }
+    if !replaced {
+        lines.push(entry);
+    }
+
+    format!(
+        "---{nl}{}{nl}---{nl}{}",
+        lines.join(newline),
+        body,
+        nl = newline
+    )
+}
+
+// Active notes whose frontmatter `field` equals `value` (or contains it, for lists)
+pub(crate) fn notes_matching(dir: &Path, field: &str, value: &str) -> Result<Vec<String>, String> {
+    let value = value.trim();
+    let mut matches = Vec::new();
+
+    for path in active_notes(dir)? {
+        let Ok(content) = fs::read_to_string(&path) else {
+            continue;
+        };
+        let matched = parse_frontmatter(&content)
+            .and_then(|f| f.get(field).map(|v| value_matches(v, value)))
+            .unwrap_or(false);
+        if matched {
+            matches.push(relative_path(dir, &path));
+        }
+    }
+    Ok(matches)
+}
+
+#[tauri::command]
+pub async fn query_notes(field: String, value: String) -> Result<Vec<String>, String> {
+    let dir = get_margherita_dir()?;
+    let matches = notes_matching(&dir, &field, &value)?;
+    println!(
+        "Found {} notes with {} = {}",
+        matches.len(),
+        field,
+        value.trim()
+    );
+    Ok(matches)
+}
+
+// Read just the frontmatter block from disk, stopping at the closing fence. A leading
+// BOM is skipped, and bytes that aren't UTF-8 are replaced rather than ending the read
+pub(crate) fn read_frontmatter_block(path: &Path) -> Result<Option<String>, String> {
+    let file = File::open(path).map_err(|e| format!("Failed to read file: {}", e))?;
+    let mut reader = BufReader::new(file);
+    let mut buf = Vec::new();
+    let mut next_line = || -> Result<Option<String>, String> {
+        buf.clear();
+        let read = reader
+            .read_until(b'\n', &mut buf)
+            .map_err(|e| format!("Failed to read file: {}", e))?;
+        Ok((read > 0).then(|| String::from_utf8_lossy(&buf).into_owned()))
+    };
+
+    match next_line()? {
+        Some(first) if first.trim_start_matches('\u{feff}').trim_end() == "---" => {}
+        _ => return Ok(None),
+    }
+
+    let mut yaml = String::new();
+    for _ in 0..MAX_FRONTMATTER_LINES {
+        let Some(line) = next_line()? else {
+            break;
+        };
+        let trimmed = line.trim_end();
+        if trimmed == "---" || trimmed == "..." {
+            return Ok(is_frontmatter_yaml(&yaml).then_some(yaml));
+        }
+        yaml.push_str(line.trim_end_matches(['\r', '\n']));
+        yaml.push('\n');
+    }
+
+    // Never closed (nearby), so it was a thematic break rather than frontmatter
+    Ok(None)
+}
+
+// Defaults from every `_defaults.md` between the vault root and the note's folder;
+// deeper folders override shallower ones
+fn folder_defaults(dir: &Path, file_path: &Path) -> Result<Map<String, Value>, String> {
+    let mut folders: Vec<&Path> = file_path
+        .ancestors()
+        .skip(1)
+        .take_while(|folder| folder.starts_with(dir))
+        .collect();
+    folders.reverse();
+
+    let mut defaults = Map::new();
+    for folder in folders {
+        let defaults_path = folder.join(DEFAULTS_FILE);
+        if defaults_path == file_path || !defaults_path.is_file() {
+            continue;
+        }
+        let Some(yaml) = read_frontmatter_block(&defaults_path)? else {
+            continue;
+        };
+        match serde_yaml::from_str::<Value>(&yaml) {
+            Ok(Value::Object(fields)) => defaults.extend(fields),
+            _ => println!("Ignoring invalid frontmatter in {:?}", defaults_path),
+        }
+    }
+    Ok(defaults)
+}
+
+// A note's own frontmatter layered over its folders' defaults; the note wins
+fn merged_frontmatter(path: &str) -> Result<Option<Value>, String> {
+    let dir = get_margherita_dir()?;
+    let file_path = resolve_in_vault(path)?;
+    let own = match read_frontmatter_block(&file_path)? {
+        Some(yaml) => Some(
+            serde_yaml::from_str::<Value>(&yaml)
+                .map_err(|e| format!("Invalid frontmatter in {}: {}", path, e))?,
+        ),
+        None => None,
+    };
+
+    let mut merged = folder_defaults(&dir, &file_path)?;
+    match own {
+        Some(Value::Object(fields)) => merged.extend(fields),
+        // Not a mapping, so there's nothing to merge defaults into
+        Some(_) => return Ok(None),
+        None if merged.is_empty() => return Ok(None),
+        None => {}
+    }
+    Ok(Some(Value::Object(merged)))
+}
+
+// A note's parsed frontmatter (with folder defaults) without reading or rendering the body
+#[tauri::command]
+pub async fn read_frontmatter(path: String) -> Result<Option<Value>, String> {
+    merged_frontmatter(&path)
+}
+
+// The frontmatter a note effectively has once `_defaults.md` files are applied
+#[tauri::command]
+pub async fn effective_frontmatter(path: String) -> Result<Value, String> {
+    Ok(merged_frontmatter(&path)?.unwrap_or_else(|| Value::Object(Map::new())))
+}
+
+// A tag as a YAML scalar, quoted only when it has to be
+fn yaml_scalar(tag: &str) -> String {
+    serde_yaml::to_string(tag)
+        .map(|s| s.trim_end().to_string())
+        .unwrap_or_else(|_| format!("{:?}", tag))
+}
+
+// Replace the `tags:` entry in a frontmatter block, keeping its flow (`[a, b]`) or
+// block (`- a`) style and indentation so unrelated lines are untouched
+fn replace_tags(yaml: &str, tags: &[String], newline: &str) -> Option<String> {
+    let lines: Vec<&str> = yaml.lines().collect();
+    let start = lines.iter().position(|l| l.starts_with("tags:"))?;
+    let inline = lines[start]["tags:".len()..].trim();
+    // Block items (and anything else indented) belong to the entry
+    let end = start
+        + 1
+        + lines[start + 1..]
+            .iter()
+            .take_while(|l| l.starts_with([' ', '\t', '-']))
+            .count();
+
+    let mut entry = Vec::new();
+    if inline.is_empty() {
+        let indent = lines[start + 1..end]
+            .iter()
+            .find_map(|l| l.find('-').map(|i| &l[..i]))
+            .unwrap_or("  ");
+        entry.push("tags:".to_string());
+        entry.extend(
+            tags.iter()
+                .map(|t| format!("{}- {}", indent, yaml_scalar(t))),
+        );
+    } else {
+        let items: Vec<String> = tags.iter().map(|t| yaml_scalar(t)).collect();
+        entry.push(format!("tags: [{}]", items.join(", ")));
+    }
+
+    let mut rewritten: Vec<String> = lines[..start].iter().map(|l| l.to_string()).collect();
+    rewritten.extend(entry);
+    rewritten.extend(lines[end..].iter().map(|l| l.to_string()));
+    Some(rewritten.join(newline))
+}
+
+// Lowercase, trim, dedupe and sort a note's frontmatter tags, leaving everything else as is
+#[tauri::command]
+pub async fn tidy_tags(path: String) -> Result<(), String> {
+    let dir = get_margherita_dir()?;
+    let file_path = resolve_in_vault(&path)?;
+    let content =
+        fs::read_to_string(&file_path).map_err(|e| format!("Failed to read file: {}", e))?;
+
+    let Some(Value::Array(items)) =
+        parse_frontmatter(&content).and_then(|f| f.get("tags").cloned())
+    else {
+        return Ok(());
+    };
+    // Numbers and booleans are kept as tags too, anything nested is left alone
+    let original: Vec<String> = items
+        .iter()
+        .filter_map(|t| match t {
+            Value::String(s) => Some(s.clone()),
+            Value::Number(n) => Some(n.to_string()),
+            Value::Bool(b) => Some(b.to_string()),
+            _ => None,
+        })
+        .collect();
+    if original.len() != items.len() {
+        return Ok(());
+    }
+    let tags: Vec<String> = original
+        .iter()
+        .map(|t| t.trim().to_lowercase())
+        .filter(|t| !t.is_empty())
+        .collect::<BTreeSet<_>>()
+        .into_iter()
+        .collect();
+    if tags == original {
+        return Ok(());
+    }
+
+    let newline = if content.contains("\r\n") {
+        "\r\n"
+    } else {
+        "\n"
+    };
+    let (Some(yaml), body) = split_frontmatter(&content) else {
+        return Ok(());
+    };
+    let Some(yaml) = replace_tags(yaml, &tags, newline) else {
+        return Ok(());
+    };
+
+    ensure_unlocked(&relative_path(&dir, &file_path))?;
+    write_atomic(
+        &file_path,
+        &format!("---{nl}{}{nl}---{nl}{}", yaml, body, nl = newline),
+    )?;
+    println!("Tidied {} tags in {}", tags.len(), path);
+    Ok(())
+}
+
+// Whether a note starts with frontmatter, and where it ends, using the same rules as
+// everything else: a nearby closing fence and a YAML mapping between the two
+#[tauri::command]
+pub async fn detect_frontmatter(content: String) -> Result<FrontmatterInfo, String> {
+    let range = frontmatter_bounds(&content).map(|(_, body)| 0..body);
+    Ok(FrontmatterInfo {
+        found: range.is_some(),
+        range,
+    })
+}
+
+// Check a note's frontmatter block and point at the first YAML error in it. Rendering
+// treats a broken block as body text; this is for surfacing why
+#[tauri::command]
+pub async fn validate_yaml_frontmatter(content: String) -> Result<(), FrontmatterError> {
+    let Some((yaml, _)) = fenced_block(&content) else {
+        return Ok(());
+    };
+    let block = &content[yaml.clone()];
+    if block.trim().is_empty() {
+        return Ok(());
+    }
+    // The block starts on the line after the opening fence
+    let first_line = 2;
+
+    match serde_yaml::from_str::<Value>(block) {
+        Ok(Value::Object(_)) => Ok(()),
+        Ok(_) => Err(FrontmatterError {
+            message: "Frontmatter must be a mapping of keys to values".to_string(),
+            line: first_line,
+            column: 1,
+        }),
+        Err(e) => {
+            let (line, column) = e
+                .location()
+                .map(|l| (l.line(), l.column()))
+                .unwrap_or((1, 1));
+            Err(FrontmatterError {
+                message: e.to_string(),
+                line: first_line + line.saturating_sub(1),
+                column,
+            })
+        }
+    }
+}
+
+// A note's own frontmatter as a mapping, empty when it has none
+fn own_frontmatter(path: &str) -> Result<Map<String, Value>, String> {
+    let file_path = resolve_in_vault(path)?;
+    match read_frontmatter_block(&file_path)? {
+        Some(yaml) => match serde_yaml::from_str::<Value>(&yaml)
+            .map_err(|e| format!("Invalid frontmatter in {}: {}", path, e))?
+        {
+            Value::Object(fields) => Ok(fields),
+            _ => Ok(Map::new()),
+        },
+        None => Ok(Map::new()),
+    }
+}
+
+// Combine two values for the same key. Lists (or a list and a scalar) are unioned,
+// mappings merge key by key, and differing scalars become {"$conflict": [a, b]}
+fn merge_values(a: Value, b: Value) -> Value {
+    match (a, b) {
+        (a, b) if a == b => a,
+        (Value::Object(a), Value::Object(b)) => Value::Object(merge_maps(a, b)),
+        (a @ Value::Array(_), b) | (a, b @ Value::Array(_)) => {
+            let mut items = Vec::new();
+            for item in [a, b].into_iter().flat_map(|v| match v {
+                Value::Array(items) => items,
+                other => vec![other],
+            }) {
+                if !items.contains(&item) {
+                    items.push(item);
+                }
+            }
+            Value::Array(items)
+        }
+        (a, b) => {
+            let mut conflict = Map::new();
+            conflict.insert("$conflict".to_string(), Value::Array(vec![a, b]));
+            Value::Object(conflict)
+        }
+    }
+}
+
+fn merge_maps(a: Map<String, Value>, b: Map<String, Value>) -> Map<String, Value> {
+    let mut merged = a;
+    for (key, value) in b {
+        let value = match merged.remove(&key) {
+            Some(existing) => merge_values(existing, value),
+            None => value,
+        };
+        merged.insert(key, value);
+    }
+    merged
+}
+
+// The frontmatter two notes would have if they were merged, for reviewing before the
+// bodies are combined. Nothing is written; conflicts are left for the user to pick
+#[tauri::command]
+pub async fn merge_frontmatter(a: String, b: String) -> Result<Value, String> {
+    Ok(Value::Object(merge_maps(
+        own_frontmatter(&a)?,
+        own_frontmatter(&b)?,
+    )))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn leading_rule_is_not_frontmatter() {
+        let content = "---\nJust a paragraph between rules.\n\n---\nBody\n";
+        assert_eq!(split_frontmatter(content), (None, content));
+    }
+
+    #[test]
+    fn crlf_opener_is_frontmatter() {
+        let content = "---\r\ntitle: Note\r\n---\r\nBody\r\n";
+        assert_eq!(
+            split_frontmatter(content),
+            (Some("title: Note\r\n"), "Body\r\n")
+        );
+    }
+
+    #[test]
+    fn unclosed_block_is_not_frontmatter() {
+        let content = "---\ntitle: Note\nBody without a closing fence\n";
+        assert_eq!(fenced_block(content), None);
+        assert_eq!(split_frontmatter(content), (None, content));
+    }
+}
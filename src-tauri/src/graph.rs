@@ -0,0 +1,594 @@
+use notify::{RecursiveMode, Watcher};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet, VecDeque};
+use std::fs;
+use std::io::{ErrorKind, Write};
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{mpsc, Arc, Mutex};
+
+use tauri::{AppHandle, Manager, State};
+
+use crate::get_margherita_dir;
+use crate::links::{markdown_links, resolve_link, wikilinks};
+use crate::mode::ensure_editable;
+use crate::settings::SettingsState;
+use crate::slug::slugify;
+use crate::text::word_count;
+use crate::vault::{
+    active_notes, is_note, parent_of, relative_path, resolve_for_write, resolve_in_vault,
+};
+use crate::wikilink::{Unresolved, WikiResolver};
+
+// Resolved note-to-note links across the vault, one entry per reference
+pub(crate) struct LinkMap {
+    pub(crate) notes: Vec<String>,
+    pub(crate) outbound: HashMap<String, Vec<String>>,
+}
+
+fn stem_key(path: &str) -> String {
+    let name = path.rsplit('/').next().unwrap_or(path);
+    let stem = name
+        .strip_suffix(".md")
+        .or_else(|| name.strip_suffix(".markdown"))
+        .unwrap_or(name);
+    stem.to_lowercase()
+}
+
+fn note_names(dir: &Path) -> Result<Vec<String>, String> {
+    Ok(active_notes(dir)?
+        .iter()
+        .map(|p| relative_path(dir, p))
+        .collect())
+}
+
+impl LinkMap {
+    pub(crate) fn build(dir: &Path) -> Result<Self, String> {
+        let notes = note_names(dir)?;
+        let known: HashSet<&str> = notes.iter().map(|n| n.as_str()).collect();
+        let resolver = WikiResolver::build(dir)?;
+
+        let mut outbound = HashMap::new();
+        for note in &notes {
+            let Ok(content) = fs::read_to_string(dir.join(note)) else {
+                continue;
+            };
+
+            let mut targets = Vec::new();
+            for link in wikilinks(&content) {
+                if let Ok(target) = resolver.resolve(&link.target) {
+                    targets.push(target.to_string());
+                }
+            }
+            for link in markdown_links(&content) {
+                if let Some(target) = resolve_link(note, &link.dest) {
+                    if known.contains(target.as_str()) {
+                        targets.push(target);
+                    }
+                }
+            }
+
+            targets.retain(|t| t != note);
+            outbound.insert(note.clone(), targets);
+        }
+
+        Ok(LinkMap { notes, outbound })
+    }
+
+    // Notes linking to each note, one entry per reference
+    pub(crate) fn inbound(&self) -> HashMap<String, Vec<String>> {
+        let mut inbound: HashMap<String, Vec<String>> = HashMap::new();
+        for (source, targets) in &self.outbound {
+            for target in targets {
+                inbound
+                    .entry(target.clone())
+                    .or_default()
+                    .push(source.clone());
+            }
+        }
+        inbound
+    }
+}
+
+// The last link map built, reused until the vault watcher sees a note or folder
+// change. While no watcher is running every call builds a fresh one
+#[derive(Default)]
+pub struct LinkMapCache {
+    cached: Mutex<Option<Arc<LinkMap>>>,
+    watching: AtomicBool,
+}
+
+impl LinkMapCache {
+    pub(crate) fn get(&self, dir: &Path) -> Result<Arc<LinkMap>, String> {
+        let mut cached = self.cached.lock().map_err(|e| e.to_string())?;
+        if self.watching.load(Ordering::SeqCst) {
+            if let Some(map) = cached.as_ref() {
+                return Ok(map.clone());
+            }
+        }
+
+        let map = Arc::new(LinkMap::build(dir)?);
+        *cached = Some(map.clone());
+        Ok(map)
+    }
+
+    fn invalidate(&self) {
+        if let Ok(mut cached) = self.cached.lock() {
+            *cached = None;
+        }
+    }
+}
+
+// Watch the vault in the background and drop the cached link map whenever a note
+// or folder changes
+pub(crate) fn watch_links(app: AppHandle) -> Result<(), String> {
+    let dir = get_margherita_dir()?;
+    fs::create_dir_all(&dir).map_err(|e| format!("Failed to create directory: {}", e))?;
+
+    let (sender, events) = mpsc::channel();
+    let mut watcher =
+        notify::recommended_watcher(sender).map_err(|e| format!("Failed to watch vault: {}", e))?;
+    watcher
+        .watch(&dir, RecursiveMode::Recursive)
+        .map_err(|e| format!("Failed to watch vault: {}", e))?;
+
+    app.state::<LinkMapCache>()
+        .watching
+        .store(true, Ordering::SeqCst);
+    println!("Watching {:?} for link changes", dir);
+
+    std::thread::spawn(move || {
+        // Kept alive for as long as the thread runs
+        let _watcher = watcher;
+        let cache = app.state::<LinkMapCache>();
+        for event in events {
+            match event {
+                // Folders have no extension; temp files and the like don't matter
+                Ok(event)
+                    if event
+                        .paths
+                        .iter()
+                        .any(|p| is_note(p) || p.extension().is_none()) =>
+                {
+                    cache.invalidate()
+                }
+                Ok(_) => {}
+                Err(e) => println!("Vault watch error: {}", e),
+            }
+        }
+        // Without the watcher the cache could go stale unnoticed
+        cache.watching.store(false, Ordering::SeqCst);
+    });
+    Ok(())
+}
+
+// YYYY-MM-DD style names are daily notes wherever they live
+fn is_date_name(path: &str) -> bool {
+    let stem = stem_key(path);
+    let bytes = stem.as_bytes();
+    bytes.len() >= 10
+        && bytes[..10].iter().enumerate().all(|(i, b)| match i {
+            4 | 7 => *b == b'-',
+            _ => b.is_ascii_digit(),
+        })
+}
+
+fn in_folder(path: &str, folder: &str) -> bool {
+    let folder = folder.trim_matches('/');
+    !folder.is_empty() && path.starts_with(&format!("{}/", folder))
+}
+
+// Notes with no links in either direction, leaving out daily notes and templates
+#[tauri::command]
+pub async fn find_orphans(
+    settings: State<'_, SettingsState>,
+    links: State<'_, LinkMapCache>,
+) -> Result<Vec<String>, String> {
+    let settings = settings.get()?;
+    let dir = get_margherita_dir()?;
+    let map = links.get(&dir)?;
+    let inbound = map.inbound();
+
+    Ok(map
+        .notes
+        .iter()
+        .filter(|note| {
+            map.outbound.get(*note).is_none_or(|t| t.is_empty()) && !inbound.contains_key(*note)
+        })
+        .filter(|note| {
+            !is_date_name(note)
+                && !in_folder(note, &settings.journal_folder)
+                && !in_folder(note, &settings.templates_folder)
+        })
+        .cloned()
+        .collect())
+}
+
+// How many links point at each note, zero included, from a single pass over the vault
+#[tauri::command]
+pub async fn backlink_counts(
+    links: State<'_, LinkMapCache>,
+) -> Result<HashMap<String, usize>, String> {
+    let dir = get_margherita_dir()?;
+    let map = links.get(&dir)?;
+
+    let mut counts: HashMap<String, usize> = map.notes.iter().map(|n| (n.clone(), 0)).collect();
+    for targets in map.outbound.values() {
+        for target in targets {
+            *counts.entry(target.clone()).or_default() += 1;
+        }
+    }
+    Ok(counts)
+}
+
+#[derive(Debug, Serialize)]
+pub struct DensityEntry {
+    path: String,
+    links: usize,
+    words: usize,
+    links_per_100_words: f32,
+}
+
+// Notes with more than `threshold` links per 100 words of prose, densest first.
+// Wikilinks and inline links both count; embeds and images don't
+#[tauri::command]
+pub async fn link_density_report(threshold: f32) -> Result<Vec<DensityEntry>, String> {
+    let dir = get_margherita_dir()?;
+    let mut entries = Vec::new();
+
+    for path in active_notes(&dir)? {
+        let Ok(content) = fs::read_to_string(&path) else {
+            continue;
+        };
+        let links = wikilinks(&content).iter().filter(|l| !l.embed).count()
+            + markdown_links(&content)
+                .iter()
+                .filter(|l| !content[l.link.clone()].starts_with('!'))
+                .count();
+        if links == 0 {
+            continue;
+        }
+
+        // A note that's nothing but links counts as if it had a single word
+        let words = word_count(&content);
+        let density = links as f32 * 100.0 / words.max(1) as f32;
+        if density > threshold {
+            entries.push(DensityEntry {
+                path: relative_path(&dir, &path),
+                links,
+                words,
+                links_per_100_words: density,
+            });
+        }
+    }
+
+    entries.sort_by(|a, b| b.links_per_100_words.total_cmp(&a.links_per_100_words));
+    Ok(entries)
+}
+
+// Neighborhoods stop growing past this many notes so the graph view stays responsive
+const MAX_NEIGHBORHOOD_NODES: usize = 200;
+
+#[derive(Debug, Serialize)]
+pub struct GraphNode {
+    path: String,
+    // Hops from the note the neighborhood was built around
+    depth: usize,
+}
+
+#[derive(Debug, Serialize)]
+pub struct GraphEdge {
+    source: String,
+    target: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct GraphData {
+    nodes: Vec<GraphNode>,
+    edges: Vec<GraphEdge>,
+    // Set when the node cap cut the neighborhood short
+    truncated: bool,
+}
+
+// Notes within `depth` links of `path`, following links in both directions, and the
+// links between them
+#[tauri::command]
+pub async fn neighborhood(
+    path: String,
+    depth: usize,
+    links: State<'_, LinkMapCache>,
+) -> Result<GraphData, String> {
+    let dir = get_margherita_dir()?;
+    let start = relative_path(&dir, &resolve_in_vault(&path)?);
+    let map = links.get(&dir)?;
+    if !map.notes.contains(&start) {
+        return Err(format!("Note not found: {}", path));
+    }
+    let inbound = map.inbound();
+    let neighbors = |note: &str| {
+        map.outbound
+            .get(note)
+            .into_iter()
+            .chain(inbound.get(note))
+            .flatten()
+            .cloned()
+            .collect::<Vec<_>>()
+    };
+
+    let mut depths: HashMap<String, usize> = HashMap::from([(start.clone(), 0)]);
+    let mut nodes = vec![GraphNode {
+        path: start.clone(),
+        depth: 0,
+    }];
+    let mut queue = VecDeque::from([start]);
+    let mut truncated = false;
+
+    'walk: while let Some(note) = queue.pop_front() {
+        let hops = depths[&note];
+        if hops == depth {
+            continue;
+        }
+        for next in neighbors(&note) {
+            if depths.contains_key(&next) {
+                continue;
+            }
+            if nodes.len() >= MAX_NEIGHBORHOOD_NODES {
+                truncated = true;
+                break 'walk;
+            }
+            depths.insert(next.clone(), hops + 1);
+            nodes.push(GraphNode {
+                path: next.clone(),
+                depth: hops + 1,
+            });
+            queue.push_back(next);
+        }
+    }
+
+    // One edge per linked pair, however many times the link appears
+    let edges: BTreeSet<(&String, &String)> = nodes
+        .iter()
+        .filter_map(|node| Some((&node.path, map.outbound.get(&node.path)?)))
+        .flat_map(|(source, targets)| targets.iter().map(move |target| (source, target)))
+        .filter(|(_, target)| depths.contains_key(*target))
+        .collect();
+    let edges = edges
+        .into_iter()
+        .map(|(source, target)| GraphEdge {
+            source: source.clone(),
+            target: target.clone(),
+        })
+        .collect();
+
+    Ok(GraphData {
+        nodes,
+        edges,
+        truncated,
+    })
+}
+
+#[derive(Debug, Clone, Copy, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum GraphFormat {
+    Json,
+    Dot,
+}
+
+#[derive(Debug, Serialize)]
+struct ExportedEdge<'a> {
+    source: &'a str,
+    target: &'a str,
+    // How many times the source references the target
+    weight: usize,
+}
+
+#[derive(Debug, Serialize)]
+struct ExportedGraph<'a> {
+    nodes: &'a [String],
+    edges: Vec<ExportedEdge<'a>>,
+}
+
+fn dot_id(path: &str) -> String {
+    format!("\"{}\"", path.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+// The whole vault's link graph for external tools: JSON with nodes and weighted edges,
+// or a Graphviz digraph. With `weights` off, every edge counts once
+#[tauri::command]
+pub async fn export_graph(
+    format: GraphFormat,
+    weights: Option<bool>,
+    links: State<'_, LinkMapCache>,
+) -> Result<String, String> {
+    let dir = get_margherita_dir()?;
+    let map = links.get(&dir)?;
+    let weights = weights.unwrap_or(true);
+
+    let mut counts: BTreeMap<(&str, &str), usize> = BTreeMap::new();
+    for (source, targets) in &map.outbound {
+        for target in targets {
+            *counts.entry((source, target)).or_default() += 1;
+        }
+    }
+    let edges: Vec<ExportedEdge> = counts
+        .into_iter()
+        .map(|((source, target), count)| ExportedEdge {
+            source,
+            target,
+            weight: if weights { count } else { 1 },
+        })
+        .collect();
+
+    match format {
+        GraphFormat::Json => serde_json::to_string_pretty(&ExportedGraph {
+            nodes: &map.notes,
+            edges,
+        })
+        .map_err(|e| format!("Failed to serialize graph: {}", e)),
+        GraphFormat::Dot => {
+            let mut dot = String::from("digraph vault {\n");
+            for note in &map.notes {
+                dot.push_str(&format!("  {};\n", dot_id(note)));
+            }
+            for edge in &edges {
+                dot.push_str(&format!(
+                    "  {} -> {}",
+                    dot_id(edge.source),
+                    dot_id(edge.target)
+                ));
+                if weights {
+                    dot.push_str(&format!(" [weight={}]", edge.weight));
+                }
+                dot.push_str(";\n");
+            }
+            dot.push_str("}\n");
+            Ok(dot)
+        }
+    }
+}
+
+// A chain of notes that embed each other and end up back at the first one
+#[derive(Debug, Serialize)]
+pub struct TransclusionCycle {
+    notes: Vec<String>,
+}
+
+// Walk the embed graph depth-first; a note already on the stack closes a cycle
+fn find_cycles(
+    note: &str,
+    embeds: &HashMap<String, Vec<String>>,
+    stack: &mut Vec<String>,
+    done: &mut HashSet<String>,
+    cycles: &mut Vec<Vec<String>>,
+) {
+    if let Some(start) = stack.iter().position(|n| n == note) {
+        let mut cycle = stack[start..].to_vec();
+        // Rotate so the same cycle found from another note compares equal
+        let first = (0..cycle.len()).min_by_key(|&i| &cycle[i]).unwrap_or(0);
+        cycle.rotate_left(first);
+        if !cycles.contains(&cycle) {
+            cycles.push(cycle);
+        }
+        return;
+    }
+    if done.contains(note) {
+        return;
+    }
+
+    stack.push(note.to_string());
+    for target in embeds.get(note).into_iter().flatten() {
+        find_cycles(target, embeds, stack, done, cycles);
+    }
+    stack.pop();
+    done.insert(note.to_string());
+}
+
+// Notes that transclude themselves through `![[...]]` embeds, directly or indirectly
+#[tauri::command]
+pub async fn check_transclusions() -> Result<Vec<TransclusionCycle>, String> {
+    let dir = get_margherita_dir()?;
+    let notes = note_names(&dir)?;
+    let resolver = WikiResolver::build(&dir)?;
+
+    let mut embeds: HashMap<String, Vec<String>> = HashMap::new();
+    for note in &notes {
+        let Ok(content) = fs::read_to_string(dir.join(note)) else {
+            continue;
+        };
+        let targets = wikilinks(&content)
+            .into_iter()
+            .filter(|link| link.embed)
+            .filter_map(|link| resolver.resolve(&link.target).ok())
+            .map(|target| target.to_string())
+            .collect();
+        embeds.insert(note.clone(), targets);
+    }
+
+    let mut cycles = Vec::new();
+    let mut done = HashSet::new();
+    for note in &notes {
+        find_cycles(note, &embeds, &mut Vec::new(), &mut done, &mut cycles);
+    }
+
+    Ok(cycles
+        .into_iter()
+        .map(|notes| TransclusionCycle { notes })
+        .collect())
+}
+
+// Create the note a dangling wikilink in `source` points at, named after the slugified
+// target next to the source note. With `backlink`, the new note links back to its source
+#[tauri::command]
+pub async fn create_from_link(
+    source: String,
+    link_name: String,
+    backlink: Option<bool>,
+) -> Result<String, String> {
+    ensure_editable()?;
+    let dir = get_margherita_dir()?;
+    let source = relative_path(&dir, &resolve_in_vault(&source)?);
+    let target = link_name
+        .split(['#', '|'])
+        .next()
+        .unwrap_or_default()
+        .trim();
+    if target.is_empty() {
+        return Err("Link has no target".to_string());
+    }
+
+    let name = target.rsplit('/').next().unwrap_or(target);
+    let slug = slugify(name);
+    if slug.is_empty() {
+        return Err(format!("Can't make a file name from {}", target));
+    }
+
+    let resolver = WikiResolver::build(&dir)?;
+    for name in [target, slug.as_str()] {
+        match resolver.resolve(name) {
+            Ok(existing) => {
+                return Err(format!(
+                    "A note for {} already exists: {}",
+                    target, existing
+                ))
+            }
+            Err(Unresolved::Ambiguous(candidates)) => {
+                return Err(format!(
+                    "Notes for {} already exist: {}",
+                    target,
+                    candidates.join(", ")
+                ))
+            }
+            Err(Unresolved::Missing) => {}
+        }
+    }
+    let folder = parent_of(&source);
+    let path = if folder.is_empty() {
+        format!("{}.md", slug)
+    } else {
+        format!("{}/{}.md", folder, slug)
+    };
+    let file_path = resolve_for_write(&path)?;
+
+    let mut content = format!("# {}\n", name);
+    if backlink.unwrap_or(false) {
+        let stem = source.rsplit('/').next().unwrap_or(&source);
+        let stem = stem.rsplit_once('.').map(|(stem, _)| stem).unwrap_or(stem);
+        content.push_str(&format!("\nLinked from [[{}]]\n", stem));
+    }
+
+    // The heading makes the link resolve by title, so the source is left as it is
+    fs::OpenOptions::new()
+        .write(true)
+        .create_new(true)
+        .open(&file_path)
+        .and_then(|mut file| file.write_all(content.as_bytes()))
+        .map_err(|e| {
+            if e.kind() == ErrorKind::AlreadyExists {
+                format!("A file named {} already exists", path)
+            } else {
+                format!("Failed to create note: {}", e)
+            }
+        })?;
+    println!("Created {} from a link in {}", path, source);
+    Ok(path)
+}
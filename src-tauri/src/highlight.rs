@@ -0,0 +1,101 @@
+use std::collections::hash_map::DefaultHasher;
+use std::collections::HashMap;
+use std::hash::{Hash, Hasher};
+use std::sync::{Mutex, OnceLock};
+
+use syntect::html::{ClassStyle, ClassedHTMLGenerator};
+use syntect::parsing::SyntaxSet;
+use syntect::util::LinesWithEndings;
+
+// Enough for every code block of a handful of open documents
+const CACHE_CAPACITY: usize = 512;
+
+type CacheKey = (String, u64);
+
+fn syntax_set() -> &'static SyntaxSet {
+    static SYNTAXES: OnceLock<SyntaxSet> = OnceLock::new();
+    SYNTAXES.get_or_init(SyntaxSet::load_defaults_newlines)
+}
+
+// Highlighted blocks keyed by (language, code hash), so re-rendering a document
+// only runs syntect over the blocks that actually changed
+fn cache() -> &'static Mutex<HashMap<CacheKey, (String, String)>> {
+    static CACHE: OnceLock<Mutex<HashMap<CacheKey, (String, String)>>> = OnceLock::new();
+    CACHE.get_or_init(|| Mutex::new(HashMap::new()))
+}
+
+fn code_hash(code: &str) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    code.hash(&mut hasher);
+    hasher.finish()
+}
+
+fn highlight_uncached(lang: &str, code: &str) -> Option<String> {
+    let syntaxes = syntax_set();
+    let syntax = syntaxes.find_syntax_by_token(lang)?;
+    let mut generator = ClassedHTMLGenerator::new_with_class_style(
+        syntax,
+        syntaxes,
+        ClassStyle::SpacedPrefixed { prefix: "hl-" },
+    );
+    for line in LinesWithEndings::from(code) {
+        generator
+            .parse_html_for_line_which_includes_newline(line)
+            .ok()?;
+    }
+    Some(generator.finalize())
+}
+
+// Highlighted HTML for a code block, or None when the language isn't known
+pub(crate) fn highlight(lang: &str, code: &str) -> Option<String> {
+    let key = (lang.to_string(), code_hash(code));
+
+    if let Ok(cache) = cache().lock() {
+        // Compare the source too, so a hash collision can't show the wrong code
+        if let Some((cached_code, html)) = cache.get(&key) {
+            if cached_code == code {
+                return Some(html.clone());
+            }
+        }
+    }
+
+    let html = highlight_uncached(lang, code)?;
+
+    if let Ok(mut cache) = cache().lock() {
+        if cache.len() >= CACHE_CAPACITY {
+            cache.clear();
+        }
+        cache.insert(key, (code.to_string(), html.clone()));
+    }
+    Some(html)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::markdown::{render_html, ParseOptions};
+
+    const DOCUMENT: &str = "# Notes\n\n```rust\nfn main() {\n    println!(\"hi\");\n}\n```\n\nSome text\n\n```python\ndef f(x):\n    return x * 2\n```\n";
+
+    fn options() -> ParseOptions {
+        serde_json::from_str(r#"{"highlight_code": true}"#).unwrap()
+    }
+
+    fn cold_render(document: &str) -> String {
+        cache().lock().unwrap().clear();
+        render_html(document, &options())
+    }
+
+    #[test]
+    fn cached_render_matches_a_full_render() {
+        let cold = cold_render(DOCUMENT);
+        let warm = render_html(DOCUMENT, &options());
+        assert!(cold.contains("hl-"));
+        assert_eq!(warm, cold);
+
+        // Editing one block leaves the other to come from the cache
+        let edited = DOCUMENT.replace("x * 2", "x * 3");
+        let warm = render_html(&edited, &options());
+        assert_eq!(warm, cold_render(&edited));
+    }
+}
@@ -0,0 +1,74 @@
+use serde::Serialize;
+use std::cmp::Reverse;
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
+use std::time::Duration;
+
+use crate::config::update_config;
+use crate::get_margherita_dir;
+use crate::util::now_millis;
+
+#[derive(Debug, Serialize)]
+pub struct OpenRecord {
+    path: String,
+    opened_at: u64,
+}
+
+// Opens not yet written to the config, so opening a note doesn't mean a disk write
+static PENDING: Mutex<Option<HashMap<String, u64>>> = Mutex::new(None);
+static FLUSH_SCHEDULED: AtomicBool = AtomicBool::new(false);
+
+// Opens within this long of each other are saved together
+const FLUSH_DELAY: Duration = Duration::from_secs(5);
+
+pub(crate) fn record_open(path: &str) -> Result<(), String> {
+    let now = now_millis() as u64;
+    PENDING
+        .lock()
+        .map_err(|e| e.to_string())?
+        .get_or_insert_with(HashMap::new)
+        .insert(path.to_string(), now);
+
+    if !FLUSH_SCHEDULED.swap(true, Ordering::SeqCst) {
+        std::thread::spawn(|| {
+            std::thread::sleep(FLUSH_DELAY);
+            if let Err(e) = flush_history() {
+                println!("Failed to save open history: {}", e);
+            }
+        });
+    }
+    Ok(())
+}
+
+// Write pending opens to the config; also called on exit
+pub(crate) fn flush_history() -> Result<(), String> {
+    FLUSH_SCHEDULED.store(false, Ordering::SeqCst);
+    let Some(pending) = PENDING.lock().map_err(|e| e.to_string())?.take() else {
+        return Ok(());
+    };
+    update_config(|config| config.opened_at.extend(pending))
+}
+
+// Notes in the order they were last opened, newest first
+#[tauri::command]
+pub async fn open_history() -> Result<Vec<OpenRecord>, String> {
+    let dir = get_margherita_dir()?;
+    flush_history()?;
+
+    let mut records = update_config(|config| {
+        // Forget notes that were deleted or renamed since they were opened
+        config.opened_at.retain(|path, _| dir.join(path).is_file());
+        config
+            .opened_at
+            .iter()
+            .map(|(path, &opened_at)| OpenRecord {
+                path: path.clone(),
+                opened_at,
+            })
+            .collect::<Vec<_>>()
+    })?;
+
+    records.sort_by_key(|r| Reverse(r.opened_at));
+    Ok(records)
+}
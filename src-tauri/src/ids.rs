@@ -0,0 +1,49 @@
+use serde_json::Value;
+
+use crate::frontmatter::{
+    parse_frontmatter, read_frontmatter_block, set_frontmatter_field, value_matches,
+};
+use crate::get_margherita_dir;
+use crate::vault::{collect_notes, relative_path};
+
+// Give a note a frontmatter `id` if it doesn't have one yet, leaving existing ids alone
+pub(crate) fn with_note_id(content: String) -> String {
+    // Any value counts, so a numeric `id: 123` isn't replaced, but a blank one doesn't
+    let has_id = parse_frontmatter(&content)
+        .and_then(|f| f.get("id").cloned())
+        .is_some_and(|id| match id {
+            Value::Null => false,
+            Value::String(s) => !s.trim().is_empty(),
+            _ => true,
+        });
+    if has_id {
+        content
+    } else {
+        set_frontmatter_field(&content, "id", &uuid::Uuid::new_v4().to_string())
+    }
+}
+
+// Find a note's current path from its frontmatter id, wherever it has been moved
+#[tauri::command]
+pub async fn resolve_by_id(id: String) -> Result<String, String> {
+    let dir = get_margherita_dir()?;
+    let id = id.trim();
+    if id.is_empty() {
+        return Err("No id given".to_string());
+    }
+
+    for path in collect_notes(&dir)? {
+        let Ok(Some(yaml)) = read_frontmatter_block(&path) else {
+            continue;
+        };
+        let matches = serde_yaml::from_str::<Value>(&yaml)
+            .ok()
+            .and_then(|f| f.get("id").cloned())
+            .is_some_and(|note_id| value_matches(&note_id, id));
+        if matches {
+            return Ok(relative_path(&dir, &path));
+        }
+    }
+
+    Err(format!("No note with id {}", id))
+}
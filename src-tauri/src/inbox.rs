@@ -0,0 +1,144 @@
+use notify::{RecursiveMode, Watcher};
+use serde::Serialize;
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc;
+use std::time::{Duration, Instant};
+
+use tauri::{AppHandle, Emitter};
+
+use crate::get_margherita_dir;
+use crate::slug::slugify;
+use crate::vault::{relative_path, unique_path};
+
+// Files dropped here by other apps are moved into the vault
+pub(crate) const INBOX_DIR: &str = "inbox";
+const ATTACHMENTS_DIR: &str = "attachments";
+
+// A file is only imported once it's gone this long without changing, so apps that
+// write in several steps aren't caught halfway
+const SETTLE_TIME: Duration = Duration::from_millis(1500);
+const POLL_INTERVAL: Duration = Duration::from_millis(500);
+
+// Extensions that become notes; plain text captures count too
+const NOTE_EXTENSIONS: &[&str] = &["md", "markdown", "txt", "text"];
+
+#[derive(Debug, Clone, Serialize)]
+struct InboxImport {
+    from: String,
+    to: String,
+}
+
+// Partial downloads and editor temp files aren't captures
+fn is_temporary(path: &Path) -> bool {
+    let name = path
+        .file_name()
+        .map(|n| n.to_string_lossy().to_lowercase())
+        .unwrap_or_default();
+    name.starts_with('.')
+        || name.ends_with('~')
+        || [".tmp", ".part", ".crdownload", ".download"]
+            .iter()
+            .any(|ext| name.ends_with(ext))
+}
+
+// Where an inbox file belongs in the vault: notes at the root as `<slug>.md`,
+// everything else in attachments/ with a slugified name
+fn import_target(dir: &Path, path: &Path) -> PathBuf {
+    let stem = path
+        .file_stem()
+        .map(|s| slugify(&s.to_string_lossy()))
+        .filter(|s| !s.is_empty())
+        .unwrap_or_else(|| "capture".to_string());
+    let extension = path
+        .extension()
+        .map(|e| e.to_string_lossy().to_lowercase())
+        .unwrap_or_default();
+
+    if NOTE_EXTENSIONS.contains(&extension.as_str()) {
+        unique_path(dir.join(format!("{}.md", stem)))
+    } else if extension.is_empty() {
+        unique_path(dir.join(ATTACHMENTS_DIR).join(stem))
+    } else {
+        unique_path(
+            dir.join(ATTACHMENTS_DIR)
+                .join(format!("{}.{}", stem, extension)),
+        )
+    }
+}
+
+fn import_file(app: &AppHandle, dir: &Path, path: &Path) -> Result<(), String> {
+    if !path.is_file() || is_temporary(path) {
+        return Ok(());
+    }
+
+    let target = import_target(dir, path);
+    if let Some(parent) = target.parent() {
+        fs::create_dir_all(parent).map_err(|e| format!("Failed to create directory: {}", e))?;
+    }
+    fs::rename(path, &target).map_err(|e| format!("Failed to import {:?}: {}", path, e))?;
+
+    let import = InboxImport {
+        from: relative_path(dir, path),
+        to: relative_path(dir, &target),
+    };
+    println!("Imported {} from the inbox as {}", import.from, import.to);
+    app.emit("inbox-imported", import)
+        .map_err(|e| format!("Failed to send inbox event: {}", e))
+}
+
+// Import whatever is already in the inbox, then keep watching it in the background
+pub(crate) fn watch_inbox(app: AppHandle) -> Result<(), String> {
+    let dir = get_margherita_dir()?;
+    let inbox = dir.join(INBOX_DIR);
+    fs::create_dir_all(&inbox).map_err(|e| format!("Failed to create inbox folder: {}", e))?;
+
+    let (sender, events) = mpsc::channel();
+    let mut watcher =
+        notify::recommended_watcher(sender).map_err(|e| format!("Failed to watch inbox: {}", e))?;
+    watcher
+        .watch(&inbox, RecursiveMode::NonRecursive)
+        .map_err(|e| format!("Failed to watch inbox: {}", e))?;
+
+    println!("Watching {:?} for new captures", inbox);
+
+    std::thread::spawn(move || {
+        // Kept alive for as long as the thread runs
+        let _watcher = watcher;
+        let mut pending: HashMap<PathBuf, Instant> = fs::read_dir(&inbox)
+            .map(|entries| {
+                entries
+                    .flatten()
+                    .map(|e| (e.path(), Instant::now()))
+                    .collect()
+            })
+            .unwrap_or_default();
+
+        loop {
+            match events.recv_timeout(POLL_INTERVAL) {
+                Ok(Ok(event)) => {
+                    for path in event.paths {
+                        pending.insert(path, Instant::now());
+                    }
+                }
+                Ok(Err(e)) => println!("Inbox watch error: {}", e),
+                Err(mpsc::RecvTimeoutError::Timeout) => {}
+                Err(mpsc::RecvTimeoutError::Disconnected) => break,
+            }
+
+            let settled: Vec<PathBuf> = pending
+                .iter()
+                .filter(|(_, changed)| changed.elapsed() >= SETTLE_TIME)
+                .map(|(path, _)| path.clone())
+                .collect();
+            for path in settled {
+                pending.remove(&path);
+                if let Err(e) = import_file(&app, &dir, &path) {
+                    println!("{}", e);
+                }
+            }
+        }
+    });
+    Ok(())
+}
@@ -0,0 +1,180 @@
+use std::fs;
+use std::ops::Range;
+use std::path::Path;
+
+use crate::frontmatter::split_frontmatter;
+use crate::get_margherita_dir;
+use crate::links::{apply_edits, code_ranges, in_ranges, wikilinks};
+use crate::markdown::{escape_html, headings};
+use crate::vault::{relative_path, resolve_in_vault};
+use crate::wikilink::WikiResolver;
+
+const DIRECTIVE_OPEN: &str = "{{include:";
+const DIRECTIVE_CLOSE: &str = "}}";
+
+// Includes nested deeper than this are left out, in case a cycle slips through
+const MAX_INCLUDE_DEPTH: usize = 8;
+
+pub(crate) fn has_includes(content: &str) -> bool {
+    content.contains(DIRECTIVE_OPEN)
+}
+
+fn warning(message: &str) -> String {
+    format!(
+        "<span class=\"include-warning\">{}</span>",
+        escape_html(message)
+    )
+}
+
+// Each `{{include: path}}` outside code, with the directive's range and its path
+fn directives(content: &str) -> Vec<(Range<usize>, &str)> {
+    let code = code_ranges(content);
+    let mut found_directives = Vec::new();
+    let mut pos = 0;
+
+    while let Some(found) = content[pos..].find(DIRECTIVE_OPEN) {
+        let start = pos + found;
+        let after_open = start + DIRECTIVE_OPEN.len();
+        let Some(close) = content[after_open..].find(DIRECTIVE_CLOSE) else {
+            break;
+        };
+        let end = after_open + close + DIRECTIVE_CLOSE.len();
+        let target = content[after_open..after_open + close].trim();
+        pos = end;
+
+        if !in_ranges(&code, start) && !target.contains('\n') {
+            found_directives.push((start..end, target));
+        }
+    }
+    found_directives
+}
+
+fn expand(content: &str, stack: &mut Vec<String>) -> String {
+    let mut expanded = String::with_capacity(content.len());
+    let mut pos = 0;
+
+    for (range, target) in directives(content) {
+        expanded.push_str(&content[pos..range.start]);
+        pos = range.end;
+
+        if stack.iter().any(|p| p == target) {
+            expanded.push_str(&warning(&format!(
+                "Include cycle: {} -> {}",
+                stack.join(" -> "),
+                target
+            )));
+        } else if stack.len() >= MAX_INCLUDE_DEPTH {
+            expanded.push_str(&warning(&format!(
+                "Includes nested too deeply at {}",
+                target
+            )));
+        } else {
+            match resolve_in_vault(target)
+                .and_then(|path| fs::read_to_string(path).map_err(|e| e.to_string()))
+            {
+                Ok(included) => {
+                    let (_, body) = split_frontmatter(&included);
+                    stack.push(target.to_string());
+                    expanded.push_str(expand(body, stack).trim_end());
+                    stack.pop();
+                }
+                Err(_) => expanded.push_str(&warning(&format!("Missing include: {}", target))),
+            }
+        }
+    }
+
+    expanded.push_str(&content[pos..]);
+    expanded
+}
+
+// Replace `{{include: path}}` directives (paths from the vault root) with the
+// included note's body, recursively. Directives in code are left as written, and
+// missing or circular includes leave a visible warning instead of failing the render
+pub(crate) fn expand_includes(content: &str) -> String {
+    expand(content, &mut Vec::new())
+}
+
+// A note's body, or just the section under the heading with id `section`
+fn section_of<'a>(body: &'a str, section: Option<&str>) -> &'a str {
+    let Some(slug) = section else {
+        return body;
+    };
+    let headings = headings(body);
+    let Some(index) = headings.iter().position(|h| h.slug == slug) else {
+        return body;
+    };
+    let end = headings[index + 1..]
+        .iter()
+        .find(|h| h.level <= headings[index].level)
+        .map_or(body.len(), |h| h.start);
+    &body[headings[index].start..end]
+}
+
+// Inline the embeds and includes in `content`, recursively. Anything that can't be
+// inlined is replaced by an HTML comment saying why
+fn flatten(dir: &Path, content: &str, resolver: &WikiResolver, stack: &mut Vec<String>) -> String {
+    // What to splice in for one embed or include of `target` (a vault-relative note)
+    let inline = |target: &str, section: Option<&str>, stack: &mut Vec<String>| {
+        if stack.iter().any(|p| p == target) {
+            return format!(
+                "<!-- Include cycle: {} -> {} -->",
+                stack.join(" -> "),
+                target
+            );
+        }
+        if stack.len() >= MAX_INCLUDE_DEPTH {
+            return format!("<!-- Includes nested too deeply at {} -->", target);
+        }
+        let Ok(included) = fs::read_to_string(dir.join(target)) else {
+            return format!("<!-- Missing include: {} -->", target);
+        };
+        let (_, body) = split_frontmatter(&included);
+        stack.push(target.to_string());
+        let flat = flatten(dir, section_of(body, section), resolver, stack);
+        stack.pop();
+        flat.trim_end().to_string()
+    };
+
+    let mut edits = Vec::new();
+    for link in wikilinks(content).into_iter().filter(|l| l.embed) {
+        // Embeds of images and other files stay as they are
+        let Ok(target) = resolver.resolve(&link.target) else {
+            continue;
+        };
+        let Some(open) = content[..link.range.start].rfind("![[") else {
+            continue;
+        };
+        let Some(close) = content[link.range.end..].find("]]") else {
+            continue;
+        };
+        let inner = &content[link.range.end..link.range.end + close];
+        let section = inner
+            .strip_prefix('#')
+            .map(|s| s.split('|').next().unwrap_or(s).trim());
+        let replacement = inline(target, section, stack);
+        edits.push((open..link.range.end + close + 2, replacement));
+    }
+    for (range, target) in directives(content) {
+        let replacement = match resolve_in_vault(target) {
+            Ok(path) => inline(&relative_path(dir, &path), None, stack),
+            Err(_) => format!("<!-- Missing include: {} -->", target),
+        };
+        edits.push((range, replacement));
+    }
+
+    apply_edits(content, edits)
+}
+
+// A note as one flat markdown document, with every `![[embed]]` and `{{include: path}}`
+// replaced by what it points at, recursively. Embedded notes lose their frontmatter
+#[tauri::command]
+pub async fn linearize(path: String) -> Result<String, String> {
+    let dir = get_margherita_dir()?;
+    let file_path = resolve_in_vault(&path)?;
+    let content =
+        fs::read_to_string(&file_path).map_err(|e| format!("Failed to read file: {}", e))?;
+    let note = relative_path(&dir, &file_path);
+    let resolver = WikiResolver::build(&dir)?;
+
+    Ok(flatten(&dir, &content, &resolver, &mut vec![note]))
+}
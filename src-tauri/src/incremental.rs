@@ -0,0 +1,208 @@
+use pulldown_cmark::{html, Event, Parser, Tag};
+use serde::Serialize;
+use std::ops::Range;
+
+use tauri::State;
+
+use crate::frontmatter::split_frontmatter;
+use crate::markdown::{headings, process_events, render_html, HeadingInfo, ParseOptions};
+use crate::settings::SettingsState;
+
+#[derive(Debug, Serialize)]
+pub struct IncrementalRender {
+    // Set when the change couldn't be patched; replaces the whole preview
+    full: Option<String>,
+    block_count: usize,
+    reused: Vec<ReusedBlock>,
+    patches: Vec<BlockPatch>,
+}
+
+// A block of the new document that renders the same as one of the old
+#[derive(Debug, Serialize)]
+pub struct ReusedBlock {
+    index: usize,
+    previous_index: usize,
+}
+
+#[derive(Debug, Serialize)]
+pub struct BlockPatch {
+    index: usize,
+    html: String,
+}
+
+// A note split into top-level blocks, with offsets into the whole note
+struct Segmented {
+    blocks: Vec<Range<usize>>,
+    // Footnotes and reference links tie blocks together, so they can't be rendered alone
+    has_shared_state: bool,
+}
+
+fn segment(content: &str, options: &ParseOptions) -> Segmented {
+    let (_, body) = split_frontmatter(content);
+    let body_offset = content.len() - body.len();
+
+    let parser = Parser::new_ext(body, options.cmark_options());
+    let mut has_shared_state = parser.reference_definitions().iter().next().is_some();
+    let mut blocks = Vec::new();
+    let mut block_start = 0;
+    let mut depth = 0;
+
+    for (event, range) in parser.into_offset_iter() {
+        if depth == 0 {
+            block_start = range.start;
+        }
+        if matches!(
+            event,
+            Event::Start(Tag::FootnoteDefinition(_)) | Event::FootnoteReference(_)
+        ) {
+            has_shared_state = true;
+        }
+        match &event {
+            Event::Start(_) => depth += 1,
+            Event::End(_) => depth -= 1,
+            _ => {}
+        }
+        if depth == 0 {
+            let end = block_start + body[block_start..range.end].trim_end().len();
+            blocks.push(body_offset + block_start..body_offset + end);
+        }
+    }
+
+    Segmented {
+        blocks,
+        has_shared_state,
+    }
+}
+
+// Slugs of the headings inside `range`, in order
+fn slugs_in<'a>(headings: &'a [HeadingInfo], range: &Range<usize>) -> Vec<&'a str> {
+    headings
+        .iter()
+        .filter(|h| range.contains(&h.start))
+        .map(|h| h.slug.as_str())
+        .collect()
+}
+
+// Render one block by itself, giving its headings the ids they have in the whole note
+fn render_block(
+    content: &str,
+    range: &Range<usize>,
+    headings: &[HeadingInfo],
+    options: &ParseOptions,
+) -> String {
+    let mut slugs = slugs_in(headings, range).into_iter();
+    let events: Vec<Event<'_>> = Parser::new_ext(&content[range.clone()], options.cmark_options())
+        .map(|event| match event {
+            Event::Start(Tag::Heading {
+                level,
+                id: None,
+                classes,
+                attrs,
+            }) => Event::Start(Tag::Heading {
+                level,
+                id: slugs.next().map(|s| s.to_string().into()),
+                classes,
+                attrs,
+            }),
+            event => event,
+        })
+        .collect();
+
+    let mut output = String::new();
+    html::push_html(&mut output, process_events(events, options).into_iter());
+    output
+}
+
+fn full_render(next: &str, block_count: usize, options: &ParseOptions) -> IncrementalRender {
+    let (_, body) = split_frontmatter(next);
+    IncrementalRender {
+        full: Some(render_html(body, options)),
+        block_count,
+        reused: Vec::new(),
+        patches: Vec::new(),
+    }
+}
+
+// Re-render only the blocks that changed between two versions of a note.
+// Unchanged blocks at the start and end are reused; if more than half the
+// note changed, a full render is cheaper than patching
+fn diff_render(prev: &str, next: &str, options: &ParseOptions) -> IncrementalRender {
+    let old = segment(prev, options);
+    let new = segment(next, options);
+    let block_count = new.blocks.len();
+
+    // Included files are only spliced in by a full render, and can change on their own
+    if old.has_shared_state
+        || new.has_shared_state
+        || options.expands_includes(prev)
+        || options.expands_includes(next)
+    {
+        return full_render(next, block_count, options);
+    }
+
+    let same = |i: usize, j: usize| prev[old.blocks[i].clone()] == next[new.blocks[j].clone()];
+    let shortest = old.blocks.len().min(block_count);
+    let prefix = (0..shortest).take_while(|&i| same(i, i)).count();
+    let suffix = (0..shortest - prefix)
+        .take_while(|&k| same(old.blocks.len() - 1 - k, block_count - 1 - k))
+        .count();
+
+    let changed = prefix..block_count - suffix;
+    if changed.len() * 2 > block_count {
+        return full_render(next, block_count, options);
+    }
+
+    // Heading ids are numbered across the note, so an edit can renumber the
+    // headings after it; those blocks would need re-rendering too
+    let old_headings = headings(prev);
+    let new_headings = headings(next);
+    let old_tail = old
+        .blocks
+        .get(old.blocks.len() - suffix)
+        .map_or(prev.len(), |b| b.start);
+    let new_tail = new
+        .blocks
+        .get(block_count - suffix)
+        .map_or(next.len(), |b| b.start);
+    if slugs_in(&old_headings, &(old_tail..prev.len()))
+        != slugs_in(&new_headings, &(new_tail..next.len()))
+    {
+        return full_render(next, block_count, options);
+    }
+
+    let offset = old.blocks.len() as isize - block_count as isize;
+    let reused = (0..prefix)
+        .chain(block_count - suffix..block_count)
+        .map(|index| ReusedBlock {
+            index,
+            previous_index: if index < prefix {
+                index
+            } else {
+                (index as isize + offset) as usize
+            },
+        })
+        .collect();
+    let patches = changed
+        .map(|index| BlockPatch {
+            index,
+            html: render_block(next, &new.blocks[index], &new_headings, options),
+        })
+        .collect();
+
+    IncrementalRender {
+        full: None,
+        block_count,
+        reused,
+        patches,
+    }
+}
+
+#[tauri::command]
+pub async fn render_incremental(
+    prev: String,
+    next: String,
+    settings: State<'_, SettingsState>,
+) -> Result<IncrementalRender, String> {
+    let options = settings.get()?.parse_options;
+    Ok(diff_render(&prev, &next, &options))
+}
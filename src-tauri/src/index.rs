@@ -0,0 +1,119 @@
+use std::collections::BTreeMap;
+use std::fs;
+
+use tauri::State;
+
+use crate::frontmatter::DEFAULTS_FILE;
+use crate::get_margherita_dir;
+use crate::links::percent_encode_path;
+use crate::lock::ensure_unlocked;
+use crate::settings::SettingsState;
+use crate::title::note_title;
+use crate::vault::{active_notes, parent_of, relative_from, relative_path, write_atomic};
+
+const INDEX_NOTE: &str = "index.md";
+
+// The generated listing sits between these, so re-running only replaces that part
+const INDEX_START: &str = "<!-- margherita:index:start -->";
+const INDEX_END: &str = "<!-- margherita:index:end -->";
+
+pub(crate) fn escape_link_text(text: &str) -> String {
+    text.replace('\\', "\\\\")
+        .replace('[', "\\[")
+        .replace(']', "\\]")
+}
+
+// Notes grouped by top-level folder (root notes first), each group sorted by path
+fn index_section(notes: &[(String, String)]) -> String {
+    let mut groups: BTreeMap<&str, Vec<&(String, String)>> = BTreeMap::new();
+    for note in notes {
+        let folder = note.0.split_once('/').map(|(top, _)| top).unwrap_or("");
+        groups.entry(folder).or_default().push(note);
+    }
+
+    let mut section = String::new();
+    for (folder, mut notes) in groups {
+        notes.sort_by_cached_key(|(path, _)| path.to_lowercase());
+        if !folder.is_empty() {
+            section.push_str(&format!("\n## {}\n\n", folder));
+        }
+        for (path, title) in notes {
+            let link = percent_encode_path(&relative_from(parent_of(INDEX_NOTE), path));
+            section.push_str(&format!("- [{}]({})\n", escape_link_text(title), link));
+        }
+    }
+    section
+}
+
+// Put `section` between the `markers`, replacing what was there before. A new note
+// gets `title` as its heading
+pub(crate) fn with_generated_section(
+    existing: Option<&str>,
+    title: &str,
+    markers: (&str, &str),
+    section: &str,
+) -> String {
+    let (start_marker, end_marker) = markers;
+    let block = format!(
+        "{}\n{}{}",
+        start_marker,
+        section.trim_start_matches('\n'),
+        end_marker
+    );
+    let Some(existing) = existing else {
+        return format!("# {}\n\n{}\n", title, block);
+    };
+
+    if let Some(start) = existing.find(start_marker) {
+        if let Some(end) = existing[start..].find(end_marker) {
+            let end = start + end + end_marker.len();
+            return format!("{}{}{}", &existing[..start], block, &existing[end..]);
+        }
+    }
+    format!("{}\n\n{}\n", existing.trim_end(), block)
+}
+
+// Write (or refresh) index.md with a link to every note, grouped by top-level folder.
+// Templates, the archive and hidden folders are left out
+#[tauri::command]
+pub async fn build_index_note(settings: State<'_, SettingsState>) -> Result<String, String> {
+    let settings = settings.get()?;
+    let dir = get_margherita_dir()?;
+    let templates = format!("{}/", settings.templates_folder.trim_matches('/'));
+
+    let mut notes = Vec::new();
+    for path in active_notes(&dir)? {
+        let relative = relative_path(&dir, &path);
+        let is_defaults = path.file_name().is_some_and(|n| n == DEFAULTS_FILE);
+        if relative == INDEX_NOTE || is_defaults || relative.starts_with(&templates) {
+            continue;
+        }
+        let content = fs::read_to_string(&path).unwrap_or_default();
+        let title = note_title(&content).unwrap_or_else(|| {
+            path.file_stem()
+                .map(|s| s.to_string_lossy().into_owned())
+                .unwrap_or_default()
+        });
+        notes.push((relative, title));
+    }
+
+    let index_path = dir.join(INDEX_NOTE);
+    let existing = if index_path.exists() {
+        ensure_unlocked(INDEX_NOTE)?;
+        Some(fs::read_to_string(&index_path).map_err(|e| format!("Failed to read index: {}", e))?)
+    } else {
+        None
+    };
+
+    let content = with_generated_section(
+        existing.as_deref(),
+        "Index",
+        (INDEX_START, INDEX_END),
+        &index_section(&notes),
+    );
+    if existing.as_deref() != Some(content.as_str()) {
+        write_atomic(&index_path, &content)?;
+    }
+    println!("Indexed {} notes in {}", notes.len(), INDEX_NOTE);
+    Ok(INDEX_NOTE.to_string())
+}
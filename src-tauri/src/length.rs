@@ -0,0 +1,38 @@
+use serde::{Deserialize, Serialize};
+
+use crate::text::{char_count, word_count};
+
+#[derive(Debug, Clone, Copy, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LengthUnit {
+    Chars,
+    Words,
+}
+
+#[derive(Debug, Serialize)]
+pub struct LengthCheck {
+    count: usize,
+    limit: usize,
+    exceeded: bool,
+    overflow: usize,
+}
+
+// Measure the readable text of a draft against a length limit, ignoring markup
+#[tauri::command]
+pub async fn check_length(
+    content: String,
+    limit: usize,
+    unit: LengthUnit,
+) -> Result<LengthCheck, String> {
+    let count = match unit {
+        LengthUnit::Chars => char_count(&content),
+        LengthUnit::Words => word_count(&content),
+    };
+
+    Ok(LengthCheck {
+        count,
+        limit,
+        exceeded: count > limit,
+        overflow: count.saturating_sub(limit),
+    })
+}
@@ -1,23 +1,95 @@
 use serde::{Deserialize, Serialize};
 use std::fs;
-use std::path::PathBuf;
+use std::io::{ErrorKind, Write};
+use std::path::{Path, PathBuf};
+
+use tauri::{RunEvent, State};
+
+mod archive;
+mod attachments;
+mod backup;
+mod cache;
+mod checksum;
+mod chunked;
+mod cleanup;
+mod clip;
+mod config;
+mod convert;
+mod encoding;
+mod error;
+mod export;
+mod extract;
+mod folders;
+mod folding;
+mod frontmatter;
+mod graph;
+mod highlight;
+mod history;
+mod ids;
+mod inbox;
+mod includes;
+mod incremental;
+mod index;
+mod length;
+mod links;
+mod lint;
+mod listing;
+mod lock;
+mod markdown;
+mod mode;
+mod readability;
+mod rename;
+mod rollup;
+mod rst;
+mod schema;
+mod scroll;
+mod search;
+mod settings;
+mod slug;
+mod snapshot;
+mod tasklist;
+mod text;
+mod timeline;
+mod title;
+mod trash;
+mod util;
+mod vault;
+mod wikilink;
+mod words;
+
+use error::FileError;
+use settings::SettingsState;
 
 #[derive(Debug, Serialize)]
 pub struct FileItem {
     name: String,
+    // Relative to the vault; the same as `name` outside of list_tree
+    path: String,
     is_dir: bool,
+    locked: bool,
+    // The entry is a symlink; is_dir and reads follow it to what it points at
+    is_symlink: bool,
+    // A folder's contents in list_tree; left out everywhere else
+    #[serde(skip_serializing_if = "Option::is_none")]
+    children: Option<Vec<FileItem>>,
 }
 
 #[derive(Debug, Deserialize)]
 pub struct SaveFileRequest {
     name: String,
     content: String,
+    // Some(false) refuses to replace an existing note; None keeps overwriting
+    overwrite: Option<bool>,
 }
 
 #[derive(Debug, Serialize)]
 pub struct FileContent {
     path: String,
     content: String,
+    // Set when the note wasn't UTF-8 and was converted from this encoding for display
+    transcoded_from: Option<String>,
+    // The file starts with a UTF-8 byte order mark, left out of `content`
+    had_bom: bool,
 }
 
 // Get the standard margherita directory in Documents
@@ -44,9 +116,9 @@ async fn ensure_margherita_dir() -> Result<(), String> {
     Ok(())
 }
 
-// List files in the margherita directory
+// List files in the margherita directory, optionally including archived notes
 #[tauri::command]
-async fn list_files() -> Result<Vec<FileItem>, String> {
+async fn list_files(include_archive: Option<bool>) -> Result<Vec<FileItem>, String> {
     let dir = get_margherita_dir()?;
 
     // Create directory if it doesn't exist
@@ -58,26 +130,36 @@ async fn list_files() -> Result<Vec<FileItem>, String> {
     }
 
     // If directory is empty, return empty vec
-    if !dir.read_dir().map_err(|e| e.to_string())?.next().is_some() {
+    if dir.read_dir().map_err(|e| e.to_string())?.next().is_none() {
         return Ok(Vec::new());
     }
 
     let entries = fs::read_dir(&dir).map_err(|e| e.to_string())?;
+    let locked = lock::locked_notes()?;
     let mut items = Vec::new();
 
     for entry in entries {
         match entry {
             Ok(entry) => {
                 let file_type = entry.file_type().map_err(|e| e.to_string())?;
+                let is_symlink = file_type.is_symlink();
+                let is_dir = if is_symlink {
+                    entry.path().is_dir()
+                } else {
+                    file_type.is_dir()
+                };
 
-                // Only show .md files
-                if let Some(ext) = entry.path().extension() {
-                    if ext == "md" {
-                        items.push(FileItem {
-                            name: entry.file_name().to_string_lossy().into_owned(),
-                            is_dir: file_type.is_dir(),
-                        });
-                    }
+                // Only show markdown files
+                if vault::is_note(&entry.path()) {
+                    let name = entry.file_name().to_string_lossy().into_owned();
+                    items.push(FileItem {
+                        locked: locked.contains(&name),
+                        path: name.clone(),
+                        name,
+                        is_dir,
+                        is_symlink,
+                        children: None,
+                    });
                 }
             }
             Err(e) => println!("Error reading entry: {}", e),
@@ -85,33 +167,93 @@ async fn list_files() -> Result<Vec<FileItem>, String> {
     }
 
     // Sort files alphabetically
-    items.sort_by(|a, b| a.name.to_lowercase().cmp(&b.name.to_lowercase()));
+    items.sort_by_key(|a| a.name.to_lowercase());
+
+    // Archived notes go after the active ones, with their archive/ prefix
+    let archive_dir = dir.join(archive::ARCHIVE_DIR);
+    if include_archive.unwrap_or(false) && archive_dir.is_dir() {
+        for path in vault::collect_notes(&archive_dir)? {
+            let name = vault::relative_path(&dir, &path);
+            items.push(FileItem {
+                locked: locked.contains(&name),
+                path: name.clone(),
+                name,
+                is_dir: false,
+                is_symlink: path.is_symlink(),
+                children: None,
+            });
+        }
+    }
 
     println!("Found {} files in margherita directory", items.len());
     Ok(items)
 }
 
 #[tauri::command]
-async fn save_file(request: SaveFileRequest) -> Result<String, String> {
+async fn save_file(
+    request: SaveFileRequest,
+    settings: State<'_, SettingsState>,
+) -> Result<String, FileError> {
     println!("save request received for file: {}", request.name);
+    if mode::is_read_mode() {
+        return Err(FileError::ReadOnlyMode(request.name));
+    }
 
     let dir = get_margherita_dir()?;
     println!("using directory: {:?}", dir);
 
-    // Ensure filename ends with .md
-    let filename = if !request.name.ends_with(".md") {
-        format!("{}.md", request.name)
+    let settings = settings.get()?;
+
+    // Ensure filename has a markdown extension, defaulting to the vault's
+    let filename = if !vault::is_note(Path::new(&request.name)) {
+        format!("{}.{}", request.name, settings.default_extension)
     } else {
         request.name
     };
 
-    let file_path = dir.join(filename);
+    let content = settings.prepare_for_save(request.content);
+
+    // Locks are keyed by the vault-relative path, so "./a.md" can't dodge one on "a.md"
+    let key = vault::relative_path(&dir, &vault::resolve_in_vault(&filename)?);
+    if lock::is_locked(&key)? {
+        return Err(FileError::Locked(key));
+    }
+    if settings.max_note_bytes > 0 && content.len() as u64 > settings.max_note_bytes {
+        return Err(FileError::TooLarge(format!(
+            "{} is {} bytes, over the vault's limit of {}",
+            filename,
+            content.len(),
+            settings.max_note_bytes
+        )));
+    }
+
+    let file_path = vault::resolve_for_write(&filename)?;
     println!("Full file path: {:?}", file_path); // Debug log
 
-    // Save the file
-    fs::write(&file_path, &request.content).map_err(|e| {
+    // Saving into a subfolder creates it
+    if let Some(parent) = file_path.parent() {
+        fs::create_dir_all(parent)
+            .map_err(|e| FileError::Io(format!("Failed to create directory: {}", e)))?;
+    }
+
+    // Save the file, creating it exclusively when overwriting isn't allowed
+    let result = if request.overwrite == Some(false) {
+        fs::OpenOptions::new()
+            .write(true)
+            .create_new(true)
+            .open(&file_path)
+            .and_then(|mut file| file.write_all(content.as_bytes()))
+    } else {
+        fs::write(&file_path, &content)
+    };
+
+    result.map_err(|e| {
         println!("Error saving file: {}", e); // Debug log
-        format!("Failed to save file: {}", e)
+        if e.kind() == ErrorKind::AlreadyExists {
+            FileError::AlreadyExists(filename)
+        } else {
+            FileError::Io(format!("Failed to save file: {}", e))
+        }
     })?;
 
     println!("Saved file: {:?}", file_path);
@@ -119,6 +261,39 @@ async fn save_file(request: SaveFileRequest) -> Result<String, String> {
     Ok(file_path.to_string_lossy().into_owned())
 }
 
+// Move a single note to the trash. Errors are tagged so the UI can tell a missing file
+// from one it isn't allowed to touch
+#[tauri::command]
+async fn delete_file(path: String) -> Result<String, FileError> {
+    println!("Delete request received for file: {}", path);
+    if mode::is_read_mode() {
+        return Err(FileError::ReadOnlyMode(path));
+    }
+
+    let dir = get_margherita_dir()?;
+    let file_path = vault::resolve_in_vault(&path).map_err(FileError::OutsideVault)?;
+    vault::ensure_inside_vault(&file_path).map_err(FileError::OutsideVault)?;
+    if !file_path.is_file() {
+        return Err(FileError::NotFound(path));
+    }
+
+    let name = vault::relative_path(&dir, &file_path);
+    if lock::is_locked(&name)? {
+        return Err(FileError::Locked(name));
+    }
+
+    let target = trash::trash_file(&dir, &file_path, util::now_millis()).map_err(|e| {
+        println!("Error deleting file: {}", e);
+        if e.kind() == ErrorKind::PermissionDenied {
+            FileError::PermissionDenied(name)
+        } else {
+            FileError::Io(format!("Failed to move file to trash: {}", e))
+        }
+    })?;
+
+    Ok(vault::relative_path(&dir, &target))
+}
+
 #[tauri::command]
 async fn read_file(path: String) -> Result<FileContent, String> {
     println!("Reading file: {}", path);
@@ -126,30 +301,200 @@ async fn read_file(path: String) -> Result<FileContent, String> {
     // Get the margherita directory
     let dir = get_margherita_dir()?;
 
-    // Combine directory path with file name
-    let file_path = dir.join(&path);
+    // Notes can live in subfolders, but never outside the vault
+    let file_path = vault::resolve_in_vault(&path)?;
     println!("Full file path: {:?}", file_path);
 
-    // Read the file content
-    let content =
-        fs::read_to_string(&file_path).map_err(|e| format!("Failed to read file: {}", e))?;
+    // Read the file content, converting notes saved in other encodings
+    let bytes = fs::read(&file_path).map_err(|e| format!("Failed to read file: {}", e))?;
+    let had_bom = encoding::has_utf8_bom(&bytes);
+    let (content, encoding) = encoding::decode(bytes);
+    let content = encoding::without_bom(content);
+    if let Some(encoding) = encoding {
+        println!("Transcoded {} from {} for display", path, encoding);
+    }
+
+    // Opening a note shouldn't fail just because the history couldn't be saved
+    if let Err(e) = history::record_open(&vault::relative_path(&dir, &file_path)) {
+        println!("Failed to record open: {}", e);
+    }
 
     Ok(FileContent {
         path, // Keep the original relative path for the UI
         content,
+        transcoded_from: encoding.map(|e| e.to_string()),
+        had_bom,
     })
 }
 
 #[cfg_attr(mobile, tauri::mobile_entry_point)]
 pub fn run() {
+    let settings = SettingsState::load();
+
+    // Clear out old trash in the background so startup isn't held up by it
+    let retention_days = settings.get().map(|s| s.trash_retention_days).unwrap_or(0);
+    if retention_days > 0 {
+        std::thread::spawn(move || {
+            if let Err(e) = trash::purge_older_than(retention_days) {
+                println!("Failed to purge trash: {}", e);
+            }
+        });
+    }
+
+    let watch_inbox = settings.get().map(|s| s.watch_inbox).unwrap_or(false);
+
     tauri::Builder::default()
         .plugin(tauri_plugin_dialog::init())
+        .setup(move |app| {
+            // A broken watcher shouldn't keep the app from starting
+            if watch_inbox {
+                if let Err(e) = inbox::watch_inbox(app.handle().clone()) {
+                    println!("{}", e);
+                }
+            }
+            // Without the watcher, link queries just rebuild the link map every time
+            if let Err(e) = graph::watch_links(app.handle().clone()) {
+                println!("{}", e);
+            }
+            Ok(())
+        })
+        .manage(trash::BulkDeleteState::default())
+        .manage(settings)
+        .manage(chunked::ChunkedSaveState::default())
+        .manage(cache::RenderCache::default())
+        .manage(graph::LinkMapCache::default())
+        .manage(listing::FileIndexCache::default())
+        .manage(search::SearchState::default())
+        .manage(words::WritingSession::default())
         .invoke_handler(tauri::generate_handler![
             ensure_margherita_dir,
             list_files,
             save_file,
-            read_file
+            read_file,
+            trash::prepare_bulk_delete,
+            trash::bulk_delete,
+            settings::get_vault_settings,
+            settings::update_vault_settings,
+            markdown::parse_markdown,
+            markdown::render_with_map,
+            markdown::render_section,
+            slug::slug_for_note,
+            lint::check_heading_structure,
+            archive::archive_file,
+            archive::unarchive_file,
+            rename::sync_filenames_to_titles,
+            frontmatter::query_notes,
+            frontmatter::read_frontmatter,
+            words::daily_word_progress,
+            export::list_themes,
+            export::export_html,
+            export::preview_with_theme,
+            graph::find_orphans,
+            chunked::begin_save,
+            chunked::write_chunk,
+            chunked::commit_save,
+            extract::extract_code_blocks,
+            convert::html_to_markdown,
+            cache::render_cache_key,
+            cache::pin_cache,
+            cache::unpin_cache,
+            schema::validate_frontmatter,
+            backup::export_zip,
+            backup::import_zip,
+            scroll::save_scroll_position,
+            scroll::get_scroll_position,
+            trash::purge_trash,
+            timeline::notes_in_range,
+            incremental::render_incremental,
+            lock::lock_file,
+            lock::unlock_file,
+            history::open_history,
+            export::render_folder,
+            encoding::transcode_to_utf8,
+            ids::resolve_by_id,
+            length::check_length,
+            links::relative_link,
+            frontmatter::effective_frontmatter,
+            encoding::fix_encoding_artifacts,
+            graph::backlink_counts,
+            extract::extract_tasks,
+            extract::all_open_tasks,
+            clip::clip_url,
+            cleanup::find_empty_notes,
+            rename::move_file,
+            checksum::file_checksum,
+            checksum::vault_checksums,
+            export::set_export_css,
+            export::get_export_css,
+            graph::check_transclusions,
+            attachments::read_attachment,
+            rename::rename_folder,
+            links::convert_links,
+            title::get_title,
+            folding::folding_ranges,
+            frontmatter::tidy_tags,
+            rename::preview_rename,
+            listing::list_files_paged,
+            frontmatter::detect_frontmatter,
+            index::build_index_note,
+            markdown::parse_markdown_timed,
+            attachments::check_attachments,
+            tasklist::tasks_to_bullets,
+            tasklist::bullets_to_tasks,
+            search::search_files,
+            search::search_files_streaming,
+            search::cancel_search,
+            graph::neighborhood,
+            snapshot::create_snapshot,
+            snapshot::list_snapshots,
+            snapshot::restore_snapshot,
+            cleanup::dedupe_consecutive_lines,
+            rst::export_to_rst,
+            graph::create_from_link,
+            markdown::parse_markdown_with_math,
+            listing::folder_counts,
+            readability::readability,
+            settings::set_pipeline,
+            graph::export_graph,
+            frontmatter::validate_yaml_frontmatter,
+            frontmatter::merge_frontmatter,
+            listing::oversized_notes,
+            export::export_by_tag,
+            backup::preview_restore,
+            backup::apply_restore,
+            mode::set_mode,
+            mode::get_mode,
+            rollup::build_rollup,
+            title::title_collisions,
+            wikilink::resolve_wikilink,
+            words::session_start,
+            words::session_delta,
+            extract::extract_tables,
+            cleanup::normalize_heading_levels,
+            markdown::resolve_anchor,
+            cleanup::format_tables,
+            encoding::has_bom,
+            encoding::strip_bom,
+            includes::linearize,
+            graph::link_density_report,
+            checksum::vault_manifest,
+            checksum::compare_manifests,
+            delete_file,
+            rename::rename_file,
+            folders::list_tree,
+            folders::create_folder,
+            folders::delete_folder
         ])
-        .run(tauri::generate_context!())
-        .expect("error while running tauri application");
+        .build(tauri::generate_context!())
+        .expect("error while building tauri application")
+        .run(|_app, event| {
+            if let RunEvent::Exit = event {
+                if let Err(e) = words::record_word_counts() {
+                    println!("Failed to record word counts: {}", e);
+                }
+                if let Err(e) = history::flush_history() {
+                    println!("Failed to save open history: {}", e);
+                }
+            }
+        });
 }
@@ -0,0 +1,467 @@
+use pulldown_cmark::{Event, LinkType, Options, Parser, Tag};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::ops::Range;
+
+use crate::get_margherita_dir;
+use crate::lock::ensure_unlocked;
+use crate::vault::{
+    normalize_relative, parent_of, relative_from, relative_path, resolve_in_vault, write_atomic,
+};
+use crate::wikilink::WikiResolver;
+
+// A `[[target#heading|alias]]` link; `range` covers just the target part.
+// `embed` marks `![[target]]` transclusions
+pub(crate) struct WikiLink {
+    pub(crate) target: String,
+    pub(crate) range: Range<usize>,
+    pub(crate) embed: bool,
+}
+
+// An inline `[text](dest)` link or image; `range` covers just the destination, `text`
+// what's between the brackets and `link` the whole thing
+pub(crate) struct MarkdownLink {
+    pub(crate) dest: String,
+    pub(crate) range: Range<usize>,
+    pub(crate) text: Range<usize>,
+    pub(crate) link: Range<usize>,
+}
+
+// Extensions that affect where links can appear; frontmatter is skipped as metadata
+fn scan_options() -> Options {
+    Options::ENABLE_TABLES
+        | Options::ENABLE_FOOTNOTES
+        | Options::ENABLE_STRIKETHROUGH
+        | Options::ENABLE_TASKLISTS
+        | Options::ENABLE_YAML_STYLE_METADATA_BLOCKS
+}
+
+// Byte ranges of code spans and code blocks, where nothing is a link
+pub(crate) fn code_ranges(content: &str) -> Vec<Range<usize>> {
+    let mut ranges = Vec::new();
+    for (event, range) in Parser::new_ext(content, scan_options()).into_offset_iter() {
+        match event {
+            Event::Code(_) | Event::Start(Tag::CodeBlock(_)) => ranges.push(range),
+            _ => {}
+        }
+    }
+    ranges
+}
+
+pub(crate) fn in_ranges(ranges: &[Range<usize>], offset: usize) -> bool {
+    ranges.iter().any(|r| r.contains(&offset))
+}
+
+pub(crate) fn wikilinks(content: &str) -> Vec<WikiLink> {
+    let code = code_ranges(content);
+    let mut links = Vec::new();
+    let mut search_from = 0;
+
+    while let Some(found) = content[search_from..].find("[[") {
+        let open = search_from + found;
+        let inner_start = open + 2;
+        let Some(close) = content[inner_start..].find("]]") else {
+            break;
+        };
+        let inner = &content[inner_start..inner_start + close];
+        search_from = inner_start + close + 2;
+
+        if inner.contains('\n') || inner.contains("[[") || in_ranges(&code, open) {
+            search_from = inner_start;
+            continue;
+        }
+
+        let target_len = inner.find(['|', '#']).unwrap_or(inner.len());
+        let target = inner[..target_len].trim();
+        if target.is_empty() {
+            continue;
+        }
+
+        let leading = inner[..target_len].len() - inner[..target_len].trim_start().len();
+        let start = inner_start + leading;
+        links.push(WikiLink {
+            target: target.to_string(),
+            range: start..start + target.len(),
+            embed: content[..open].ends_with('!'),
+        });
+    }
+    links
+}
+
+pub(crate) fn markdown_links(content: &str) -> Vec<MarkdownLink> {
+    let mut links = Vec::new();
+    for (event, range) in Parser::new_ext(content, scan_options()).into_offset_iter() {
+        let dest = match event {
+            Event::Start(Tag::Link {
+                link_type: LinkType::Inline,
+                dest_url,
+                ..
+            })
+            | Event::Start(Tag::Image {
+                link_type: LinkType::Inline,
+                dest_url,
+                ..
+            }) => dest_url,
+            _ => continue,
+        };
+        if dest.is_empty() {
+            continue;
+        }
+
+        // The parser only gives the whole link's range, so find the destination in it
+        let source = &content[range.clone()];
+        let Some(paren) = source.rfind("](") else {
+            continue;
+        };
+        if let Some(pos) = source[paren..].find(dest.as_ref()) {
+            let start = range.start + paren + pos;
+            let text_start = range.start + source.find('[').map_or(0, |i| i + 1);
+            links.push(MarkdownLink {
+                dest: dest.to_string(),
+                range: start..start + dest.len(),
+                text: text_start..range.start + paren,
+                link: range.clone(),
+            });
+        }
+    }
+    links
+}
+
+pub(crate) fn is_external(dest: &str) -> bool {
+    dest.contains("://") || dest.starts_with("mailto:") || dest.starts_with("data:")
+}
+
+fn hex_value(byte: u8) -> Option<u8> {
+    (byte as char).to_digit(16).map(|d| d as u8)
+}
+
+pub(crate) fn percent_decode(text: &str) -> String {
+    let bytes = text.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        if bytes[i] == b'%' && i + 2 < bytes.len() {
+            if let (Some(high), Some(low)) = (hex_value(bytes[i + 1]), hex_value(bytes[i + 2])) {
+                decoded.push(high * 16 + low);
+                i += 3;
+                continue;
+            }
+        }
+        decoded.push(bytes[i]);
+        i += 1;
+    }
+    String::from_utf8_lossy(&decoded).into_owned()
+}
+
+pub(crate) fn percent_encode_path(path: &str) -> String {
+    let mut encoded = String::with_capacity(path.len());
+    for c in path.chars() {
+        match c {
+            ' ' => encoded.push_str("%20"),
+            '(' => encoded.push_str("%28"),
+            ')' => encoded.push_str("%29"),
+            '#' => encoded.push_str("%23"),
+            '?' => encoded.push_str("%3F"),
+            '%' => encoded.push_str("%25"),
+            _ => encoded.push(c),
+        }
+    }
+    encoded
+}
+
+// Split a link destination into its path and any `#fragment`/`?query` suffix
+pub(crate) fn split_fragment(dest: &str) -> (&str, &str) {
+    match dest.find(['#', '?']) {
+        Some(i) => (&dest[..i], &dest[i..]),
+        None => (dest, ""),
+    }
+}
+
+// Resolve a markdown link from the note at `from` to a vault-relative path
+pub(crate) fn resolve_link(from: &str, dest: &str) -> Option<String> {
+    if is_external(dest) || dest.starts_with('#') {
+        return None;
+    }
+    let (path, _) = split_fragment(dest);
+    if path.is_empty() {
+        return None;
+    }
+    let path = percent_decode(path);
+    if let Some(absolute) = path.strip_prefix('/') {
+        return normalize_relative(absolute);
+    }
+    normalize_relative(&format!("{}/{}", parent_of(from), path))
+}
+
+pub(crate) fn strip_note_extension(path: &str) -> &str {
+    path.strip_suffix(".md")
+        .or_else(|| path.strip_suffix(".markdown"))
+        .unwrap_or(path)
+}
+
+pub(crate) fn file_stem(path: &str) -> &str {
+    strip_note_extension(path.rsplit('/').next().unwrap_or(path))
+}
+
+pub(crate) fn apply_edits(content: &str, mut edits: Vec<(Range<usize>, String)>) -> String {
+    edits.sort_by_key(|(range, _)| range.start);
+    let mut result = String::with_capacity(content.len());
+    let mut last = 0;
+    for (range, replacement) in edits {
+        if range.start < last {
+            continue;
+        }
+        result.push_str(&content[last..range.start]);
+        result.push_str(&replacement);
+        last = range.end;
+    }
+    result.push_str(&content[last..]);
+    result
+}
+
+// Rewrite the links in a note (located at `note`) that point at `old` so they point at
+// `new`. Wikilinks are resolved with `before` (the vault as it was) and only rewritten
+// when `after` would no longer take them to the renamed note, e.g. not when they match
+// its title
+pub(crate) fn rewrite_links(
+    content: &str,
+    note: &str,
+    old: &str,
+    new: &str,
+    before: &WikiResolver,
+    after: &WikiResolver,
+) -> String {
+    let mut edits = Vec::new();
+
+    for link in wikilinks(content) {
+        if before.resolve(&link.target).ok() != Some(old)
+            || after.resolve(&link.target).ok() == Some(new)
+        {
+            continue;
+        }
+        let keep_extension = link.target.ends_with(".md") || link.target.ends_with(".markdown");
+        // A bare name only works while no other note shares it
+        let name = new.rsplit('/').next().unwrap_or(new);
+        let replacement = if link.target.contains('/') || after.resolve(name).ok() != Some(new) {
+            new
+        } else {
+            name
+        };
+        let replacement = if keep_extension {
+            replacement
+        } else {
+            strip_note_extension(replacement)
+        };
+        edits.push((link.range, replacement.to_string()));
+    }
+
+    for link in markdown_links(content) {
+        if resolve_link(note, &link.dest).as_deref() != Some(old) {
+            continue;
+        }
+        let (_, fragment) = split_fragment(&link.dest);
+        let path = if link.dest.starts_with('/') {
+            format!("/{}", new)
+        } else {
+            relative_from(parent_of(note), new)
+        };
+        edits.push((
+            link.range,
+            format!("{}{}", percent_encode_path(&path), fragment),
+        ));
+    }
+
+    apply_edits(content, edits)
+}
+
+// Rewrite the relative links in a note moved from `old` to `new` so they keep pointing
+// at the same files. Only links that resolved to an existing file from the old spot and
+// wouldn't from the new one are touched; remote and vault-absolute links are left alone
+pub(crate) fn rebase_links(
+    content: &str,
+    old: &str,
+    new: &str,
+    exists: impl Fn(&str) -> bool,
+) -> String {
+    let mut edits = Vec::new();
+
+    for link in markdown_links(content) {
+        if link.dest.starts_with('/') {
+            continue;
+        }
+        let Some(target) = resolve_link(old, &link.dest) else {
+            continue;
+        };
+        // A link to the note itself follows it to its new location
+        let target = if target == old {
+            new.to_string()
+        } else {
+            target
+        };
+        if (target != new && !exists(&target))
+            || resolve_link(new, &link.dest).as_deref() == Some(target.as_str())
+        {
+            continue;
+        }
+
+        let (_, fragment) = split_fragment(&link.dest);
+        let path = relative_from(parent_of(new), &target);
+        edits.push((
+            link.range,
+            format!("{}{}", percent_encode_path(&path), fragment),
+        ));
+    }
+
+    apply_edits(content, edits)
+}
+
+// Where a vault-relative path ends up once folder `old` is renamed to `new`
+fn moved_path(path: &str, old: &str, new: &str) -> Option<String> {
+    path.strip_prefix(old)
+        .and_then(|rest| rest.strip_prefix('/'))
+        .map(|rest| format!("{}/{}", new, rest))
+}
+
+// Fix the links in a note after folder `old` was renamed to `new`. The note was at
+// `before` and is now at `now` (they differ when it was inside the folder). Links
+// into the folder follow it, and relative links out of a moved note are rebased
+pub(crate) fn rewrite_for_folder_move(
+    content: &str,
+    before: &str,
+    now: &str,
+    old: &str,
+    new: &str,
+    exists: impl Fn(&str) -> bool,
+) -> String {
+    let mut edits = Vec::new();
+
+    for link in markdown_links(content) {
+        let Some(target) = resolve_link(before, &link.dest) else {
+            continue;
+        };
+        let target = moved_path(&target, old, new).unwrap_or(target);
+        if !exists(&target) || resolve_link(now, &link.dest).as_deref() == Some(target.as_str()) {
+            continue;
+        }
+
+        let (_, fragment) = split_fragment(&link.dest);
+        let path = if link.dest.starts_with('/') {
+            format!("/{}", target)
+        } else {
+            relative_from(parent_of(now), &target)
+        };
+        edits.push((
+            link.range,
+            format!("{}{}", percent_encode_path(&path), fragment),
+        ));
+    }
+
+    // Only path-qualified wikilinks name the folder; bare ones resolve by file name
+    for link in wikilinks(content) {
+        let target = link.target.trim_start_matches('/');
+        if let Some(moved) = moved_path(target, old, new) {
+            edits.push((link.range, moved));
+        }
+    }
+
+    apply_edits(content, edits)
+}
+
+// Vault-relative form of a note path, rejecting anything outside the vault
+fn vault_relative(path: &str) -> Result<String, String> {
+    resolve_in_vault(path)?;
+    normalize_relative(&path.replace('\\', "/"))
+        .filter(|p| !p.is_empty())
+        .ok_or_else(|| format!("Path is outside the margherita directory: {}", path))
+}
+
+// The link destination to write in `from` so it points at `to`, e.g. "../work/my%20note.md"
+#[tauri::command]
+pub async fn relative_link(from: String, to: String) -> Result<String, String> {
+    let from = vault_relative(&from)?;
+    let to = vault_relative(&to)?;
+    Ok(percent_encode_path(&relative_from(parent_of(&from), &to)))
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum LinkStyle {
+    // `../folder/note.md`, relative to the linking note
+    Relative,
+    // `/folder/note.md`, from the vault root
+    VaultAbsolute,
+}
+
+#[derive(Debug, Serialize)]
+pub struct LinkConversion {
+    converted: usize,
+    // Internal links that don't point at an existing file, left as they were
+    unresolved: Vec<String>,
+}
+
+// Rewrite a note's internal markdown links to `style`; wikilinks and remote links are left alone
+fn convert_link_style(
+    content: &str,
+    note: &str,
+    style: LinkStyle,
+    exists: impl Fn(&str) -> bool,
+) -> (String, LinkConversion) {
+    let mut edits = Vec::new();
+    let mut unresolved = Vec::new();
+
+    for link in markdown_links(content) {
+        let Some(target) = resolve_link(note, &link.dest) else {
+            continue;
+        };
+        if !exists(&target) {
+            unresolved.push(link.dest);
+            continue;
+        }
+
+        let is_absolute = link.dest.starts_with('/');
+        let path = match style {
+            LinkStyle::Relative if is_absolute => relative_from(parent_of(note), &target),
+            LinkStyle::VaultAbsolute if !is_absolute => format!("/{}", target),
+            _ => continue,
+        };
+        let (_, fragment) = split_fragment(&link.dest);
+        edits.push((
+            link.range,
+            format!("{}{}", percent_encode_path(&path), fragment),
+        ));
+    }
+
+    let converted = edits.len();
+    (
+        apply_edits(content, edits),
+        LinkConversion {
+            converted,
+            unresolved,
+        },
+    )
+}
+
+// Switch a note's internal links between relative and vault-absolute paths
+#[tauri::command]
+pub async fn convert_links(path: String, style: LinkStyle) -> Result<LinkConversion, String> {
+    let dir = get_margherita_dir()?;
+    let file_path = resolve_in_vault(&path)?;
+    let note = relative_path(&dir, &file_path);
+    ensure_unlocked(&note)?;
+
+    let content =
+        fs::read_to_string(&file_path).map_err(|e| format!("Failed to read file: {}", e))?;
+    let (converted, report) = convert_link_style(&content, &note, style, |p| dir.join(p).exists());
+
+    if report.converted > 0 {
+        write_atomic(&file_path, &converted)?;
+    }
+    if !report.unresolved.is_empty() {
+        println!(
+            "Left {} unresolved links in {}",
+            report.unresolved.len(),
+            note
+        );
+    }
+    Ok(report)
+}
@@ -0,0 +1,37 @@
+use serde::Serialize;
+
+use crate::markdown::headings;
+
+#[derive(Debug, Serialize)]
+pub struct StructureWarning {
+    line: usize,
+    level: u8,
+    previous_level: u8,
+    message: String,
+}
+
+// Report headings that skip a level (e.g. H1 followed directly by H3)
+#[tauri::command]
+pub async fn check_heading_structure(content: String) -> Result<Vec<StructureWarning>, String> {
+    let mut warnings = Vec::new();
+    let mut previous: Option<u8> = None;
+
+    for heading in headings(&content) {
+        if let Some(previous_level) = previous {
+            if heading.level > previous_level + 1 {
+                warnings.push(StructureWarning {
+                    line: heading.line,
+                    level: heading.level,
+                    previous_level,
+                    message: format!(
+                        "Heading level jumps from H{} to H{}",
+                        previous_level, heading.level
+                    ),
+                });
+            }
+        }
+        previous = Some(heading.level);
+    }
+
+    Ok(warnings)
+}
@@ -0,0 +1,180 @@
+use serde::{Deserialize, Serialize};
+use std::cmp::Reverse;
+use std::collections::HashMap;
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant, SystemTime};
+
+use tauri::State;
+
+use crate::archive::ARCHIVE_DIR;
+use crate::get_margherita_dir;
+use crate::lock::locked_notes;
+use crate::settings::SettingsState;
+use crate::vault::{parent_of, relative_path, walk_notes};
+use crate::FileItem;
+
+// A sorted index is reused for a few seconds so scrolling through pages doesn't re-walk the vault
+const INDEX_TTL: Duration = Duration::from_secs(5);
+
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SortBy {
+    Name,
+    // Most recently modified first
+    Modified,
+    // Largest first
+    Size,
+}
+
+#[derive(Debug, Serialize)]
+pub struct PagedFiles {
+    items: Vec<FileItem>,
+    total: usize,
+}
+
+struct IndexedNote {
+    path: String,
+    modified: SystemTime,
+    size: u64,
+    is_symlink: bool,
+}
+
+struct SortedIndex {
+    sort: SortBy,
+    built_at: Instant,
+    notes: Arc<Vec<IndexedNote>>,
+}
+
+#[derive(Default)]
+pub struct FileIndexCache {
+    cached: Mutex<Option<SortedIndex>>,
+}
+
+// Every active note with its metadata, gathered in one walk over the vault
+fn index_notes(dir: &Path) -> Result<Vec<IndexedNote>, String> {
+    let archive_dir = dir.join(ARCHIVE_DIR);
+    Ok(walk_notes(dir)?
+        .into_iter()
+        .filter(|n| !n.path.starts_with(&archive_dir))
+        .map(|n| IndexedNote {
+            path: relative_path(dir, &n.path),
+            modified: n.meta.modified().unwrap_or(SystemTime::UNIX_EPOCH),
+            size: n.meta.len(),
+            is_symlink: n.is_symlink,
+        })
+        .collect())
+}
+
+impl FileIndexCache {
+    fn get(&self, dir: &Path, sort: SortBy) -> Result<Arc<Vec<IndexedNote>>, String> {
+        let mut cached = self.cached.lock().map_err(|e| e.to_string())?;
+        if let Some(index) = cached.as_ref() {
+            if index.sort == sort && index.built_at.elapsed() < INDEX_TTL {
+                return Ok(index.notes.clone());
+            }
+        }
+
+        let mut notes = index_notes(dir)?;
+        match sort {
+            SortBy::Name => notes.sort_by_cached_key(|n| n.path.to_lowercase()),
+            SortBy::Modified => notes.sort_by_key(|n| Reverse(n.modified)),
+            SortBy::Size => notes.sort_by_key(|n| Reverse(n.size)),
+        }
+
+        let notes = Arc::new(notes);
+        *cached = Some(SortedIndex {
+            sort,
+            built_at: Instant::now(),
+            notes: notes.clone(),
+        });
+        Ok(notes)
+    }
+}
+
+// One page of the vault's notes (recursively, without the archive) plus the total count
+#[tauri::command]
+pub async fn list_files_paged(
+    offset: usize,
+    limit: usize,
+    sort: SortBy,
+    index: State<'_, FileIndexCache>,
+) -> Result<PagedFiles, String> {
+    let dir = get_margherita_dir()?;
+    if !dir.exists() {
+        return Ok(PagedFiles {
+            items: Vec::new(),
+            total: 0,
+        });
+    }
+
+    let notes = index.get(&dir, sort)?;
+    let locked = locked_notes()?;
+    let items = notes
+        .iter()
+        .skip(offset)
+        .take(limit)
+        .map(|n| FileItem {
+            name: n.path.clone(),
+            path: n.path.clone(),
+            is_dir: false,
+            locked: locked.contains(&n.path),
+            is_symlink: n.is_symlink,
+            children: None,
+        })
+        .collect();
+
+    Ok(PagedFiles {
+        items,
+        total: notes.len(),
+    })
+}
+
+// How many notes each folder holds ("" is the vault root), from one walk over the vault.
+// With `recursive`, a folder's count includes everything below it
+#[tauri::command]
+pub async fn folder_counts(recursive: Option<bool>) -> Result<HashMap<String, usize>, String> {
+    let dir = get_margherita_dir()?;
+    let recursive = recursive.unwrap_or(false);
+    let mut counts: HashMap<String, usize> = HashMap::new();
+
+    for note in index_notes(&dir)? {
+        let mut folder = parent_of(&note.path);
+        *counts.entry(folder.to_string()).or_default() += 1;
+        while recursive && !folder.is_empty() {
+            folder = parent_of(folder);
+            *counts.entry(folder.to_string()).or_default() += 1;
+        }
+    }
+    Ok(counts)
+}
+
+// Notes already bigger than the vault's `max_note_bytes`, largest first; empty when
+// there's no limit
+#[tauri::command]
+pub async fn oversized_notes(settings: State<'_, SettingsState>) -> Result<Vec<FileItem>, String> {
+    let limit = settings.get()?.max_note_bytes;
+    let dir = get_margherita_dir()?;
+    if limit == 0 || !dir.exists() {
+        return Ok(Vec::new());
+    }
+
+    let mut notes: Vec<IndexedNote> = index_notes(&dir)?
+        .into_iter()
+        .filter(|n| n.size > limit)
+        .collect();
+    notes.sort_by_key(|n| Reverse(n.size));
+
+    let locked = locked_notes()?;
+    Ok(notes
+        .into_iter()
+        .map(|n| FileItem {
+            locked: locked.contains(&n.path),
+            name: n.path.clone(),
+            path: n.path,
+            is_dir: false,
+            is_symlink: n.is_symlink,
+            children: None,
+        })
+        .collect())
+}
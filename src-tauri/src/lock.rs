@@ -0,0 +1,49 @@
+use std::collections::BTreeSet;
+
+use crate::config::{read_config, update_config};
+use crate::get_margherita_dir;
+use crate::vault::{relative_path, resolve_in_vault};
+
+pub(crate) fn locked_notes() -> Result<BTreeSet<String>, String> {
+    Ok(read_config()?.locked_notes)
+}
+
+pub(crate) fn is_locked(path: &str) -> Result<bool, String> {
+    Ok(locked_notes()?.contains(path))
+}
+
+// For commands that report plain string errors
+pub(crate) fn ensure_unlocked(path: &str) -> Result<(), String> {
+    if is_locked(path)? {
+        return Err(format!("Note is locked: {}", path));
+    }
+    Ok(())
+}
+
+// Vault-relative form of `path`, so "./a.md" and "a.md" lock the same note
+fn lock_key(path: &str) -> Result<String, String> {
+    let dir = get_margherita_dir()?;
+    let file_path = resolve_in_vault(path)?;
+    if !file_path.is_file() {
+        return Err(format!("File not found: {}", path));
+    }
+    Ok(relative_path(&dir, &file_path))
+}
+
+// Protect a note from being saved, renamed, moved, or deleted until it's unlocked
+#[tauri::command]
+pub async fn lock_file(path: String) -> Result<(), String> {
+    let key = lock_key(&path)?;
+    update_config(|config| config.locked_notes.insert(key))?;
+    println!("Locked {}", path);
+    Ok(())
+}
+
+#[tauri::command]
+pub async fn unlock_file(path: String) -> Result<(), String> {
+    let dir = get_margherita_dir()?;
+    let key = relative_path(&dir, &resolve_in_vault(&path)?);
+    update_config(|config| config.locked_notes.remove(&key))?;
+    println!("Unlocked {}", path);
+    Ok(())
+}
@@ -0,0 +1,1110 @@
+use pulldown_cmark::{html, CodeBlockKind, Event, HeadingLevel, Options, Parser, Tag, TagEnd};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs;
+use std::time::{Duration, Instant};
+
+use tauri::State;
+
+use crate::cache::{cache_key, RenderCache};
+use crate::frontmatter::split_frontmatter;
+use crate::highlight::highlight;
+use crate::includes::{expand_includes, has_includes};
+use crate::links::percent_decode;
+use crate::settings::SettingsState;
+use crate::slug::slugify;
+use crate::vault::resolve_in_vault;
+
+// Markdown extensions that can be toggled per vault or per call
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ParseOptions {
+    tables: bool,
+    footnotes: bool,
+    // Collect footnote definitions into a numbered list at the end of the document
+    footnotes_at_end: bool,
+    // Heading above that list
+    footnotes_heading: String,
+    strikethrough: bool,
+    tasklists: bool,
+    // Curly quotes, en/em dashes and ellipses; off so output stays literal by default
+    smart_punctuation: bool,
+    // Open http(s) links in a new window, without leaking the opener
+    external_links_blank: bool,
+    // Syntax-highlight fenced code blocks with a known language
+    highlight_code: bool,
+    // Emit ```mermaid blocks as containers for mermaid.js instead of code
+    mermaid: bool,
+    // Splice `{{include: path}}` directives in before rendering
+    includes: bool,
+    // Expand tabs in code blocks to this many columns; 0 leaves them as tabs
+    tab_width: u8,
+    // Post-processing passes in the order they run; a pass left out is skipped
+    pipeline: Vec<Stage>,
+}
+
+// A named post-processing pass over the parser's events
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Stage {
+    HeadingIds,
+    Mermaid,
+    Tabs,
+    Highlight,
+    ExternalLinks,
+}
+
+const DEFAULT_PIPELINE: [Stage; 5] = [
+    Stage::HeadingIds,
+    Stage::Mermaid,
+    Stage::Tabs,
+    Stage::Highlight,
+    Stage::ExternalLinks,
+];
+
+impl Stage {
+    fn name(self) -> &'static str {
+        match self {
+            Stage::HeadingIds => "heading_ids",
+            Stage::Mermaid => "mermaid",
+            Stage::Tabs => "tabs",
+            Stage::Highlight => "highlight",
+            Stage::ExternalLinks => "external_links",
+        }
+    }
+}
+
+// Parse a pipeline from stage names, rejecting unknown or repeated stages
+pub(crate) fn parse_pipeline(names: &[String]) -> Result<Vec<Stage>, String> {
+    let mut stages = Vec::new();
+    for name in names {
+        let stage = DEFAULT_PIPELINE
+            .into_iter()
+            .find(|s| s.name() == name.trim())
+            .ok_or_else(|| {
+                let known: Vec<&str> = DEFAULT_PIPELINE.iter().map(|s| s.name()).collect();
+                format!(
+                    "Unknown stage: {} (expected one of {})",
+                    name,
+                    known.join(", ")
+                )
+            })?;
+        if stages.contains(&stage) {
+            return Err(format!("Stage listed twice: {}", name));
+        }
+        stages.push(stage);
+    }
+    Ok(stages)
+}
+
+impl Default for ParseOptions {
+    fn default() -> Self {
+        ParseOptions {
+            tables: true,
+            footnotes: true,
+            footnotes_at_end: false,
+            footnotes_heading: "Footnotes".to_string(),
+            strikethrough: true,
+            tasklists: true,
+            smart_punctuation: false,
+            external_links_blank: true,
+            highlight_code: false,
+            mermaid: true,
+            includes: true,
+            tab_width: 4,
+            pipeline: DEFAULT_PIPELINE.to_vec(),
+        }
+    }
+}
+
+impl ParseOptions {
+    pub(crate) fn set_pipeline(&mut self, pipeline: Vec<Stage>) {
+        self.pipeline = pipeline;
+    }
+
+    // Whether rendering `content` splices in other files
+    pub(crate) fn expands_includes(&self, content: &str) -> bool {
+        self.includes && has_includes(content)
+    }
+
+    pub(crate) fn cmark_options(&self) -> Options {
+        let mut options = Options::empty();
+        if self.tables {
+            options.insert(Options::ENABLE_TABLES);
+        }
+        if self.footnotes {
+            options.insert(Options::ENABLE_FOOTNOTES);
+        }
+        if self.strikethrough {
+            options.insert(Options::ENABLE_STRIKETHROUGH);
+        }
+        if self.tasklists {
+            options.insert(Options::ENABLE_TASKLISTS);
+        }
+        if self.smart_punctuation {
+            options.insert(Options::ENABLE_SMART_PUNCTUATION);
+        }
+        options
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct RenderMap {
+    html: String,
+    blocks: Vec<BlockRange>,
+}
+
+// Where a top-level block lives in the source and in the rendered HTML
+#[derive(Debug, Serialize)]
+pub struct BlockRange {
+    start_byte: usize,
+    end_byte: usize,
+    start_line: usize,
+    end_line: usize,
+    html_start: usize,
+    html_end: usize,
+}
+
+// Maps byte offsets to 1-based line numbers
+pub(crate) struct LineIndex {
+    starts: Vec<usize>,
+}
+
+impl LineIndex {
+    pub(crate) fn new(text: &str) -> Self {
+        let mut starts = vec![0];
+        starts.extend(text.match_indices('\n').map(|(i, _)| i + 1));
+        LineIndex { starts }
+    }
+
+    pub(crate) fn line(&self, offset: usize) -> usize {
+        match self.starts.binary_search(&offset) {
+            Ok(i) => i + 1,
+            Err(i) => i,
+        }
+    }
+}
+
+// A heading found in the source, with the id it gets when rendered
+pub(crate) struct HeadingInfo {
+    pub(crate) level: u8,
+    pub(crate) text: String,
+    pub(crate) slug: String,
+    pub(crate) line: usize,
+    pub(crate) start: usize,
+}
+
+// Hands out heading ids, suffixing repeats GitHub-style (intro, intro-1, ...)
+#[derive(Default)]
+pub(crate) struct SlugCounter {
+    seen: HashMap<String, usize>,
+}
+
+impl SlugCounter {
+    pub(crate) fn next(&mut self, text: &str) -> String {
+        let mut base = slugify(text);
+        if base.is_empty() {
+            base = "section".to_string();
+        }
+        let count = self.seen.entry(base.clone()).or_insert(0);
+        let slug = if *count == 0 {
+            base
+        } else {
+            format!("{}-{}", base, count)
+        };
+        *count += 1;
+        slug
+    }
+}
+
+// Every heading in the note body, in document order; code blocks are skipped
+pub(crate) fn headings(content: &str) -> Vec<HeadingInfo> {
+    let (_, body) = split_frontmatter(content);
+    let body_offset = content.len() - body.len();
+    let lines = LineIndex::new(content);
+
+    let mut headings = Vec::new();
+    let mut slugs = SlugCounter::default();
+    let mut current: Option<HeadingInfo> = None;
+
+    for (event, range) in Parser::new_ext(body, Options::empty()).into_offset_iter() {
+        match event {
+            Event::Start(Tag::Heading { level, .. }) => {
+                let start = body_offset + range.start;
+                current = Some(HeadingInfo {
+                    level: level as u8,
+                    text: String::new(),
+                    slug: String::new(),
+                    line: lines.line(start),
+                    start,
+                });
+            }
+            Event::End(TagEnd::Heading(_)) => {
+                if let Some(mut heading) = current.take() {
+                    heading.text = heading.text.trim().to_string();
+                    heading.slug = slugs.next(&heading.text);
+                    headings.push(heading);
+                }
+            }
+            Event::Text(t) | Event::Code(t) => {
+                if let Some(heading) = current.as_mut() {
+                    heading.text.push_str(&t);
+                }
+            }
+            _ => {}
+        }
+    }
+    headings
+}
+
+// Give every heading an id so sections can be linked and rendered on their own
+fn with_heading_ids(mut events: Vec<Event<'_>>) -> Vec<Event<'_>> {
+    let mut slugs = SlugCounter::default();
+    let mut open: Option<(usize, String)> = None;
+
+    for i in 0..events.len() {
+        match &events[i] {
+            Event::Start(Tag::Heading { id: None, .. }) => open = Some((i, String::new())),
+            Event::Text(t) | Event::Code(t) => {
+                if let Some((_, text)) = open.as_mut() {
+                    text.push_str(t);
+                }
+            }
+            Event::End(TagEnd::Heading(_)) => {
+                if let Some((start, text)) = open.take() {
+                    let slug = slugs.next(text.trim());
+                    if let Event::Start(Tag::Heading { id, .. }) = &mut events[start] {
+                        *id = Some(slug.into());
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+    events
+}
+
+pub(crate) fn escape_html(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&#39;"),
+            _ => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+fn is_external(url: &str) -> bool {
+    let url = url.trim_start().to_ascii_lowercase();
+    url.starts_with("http://") || url.starts_with("https://")
+}
+
+// Render external links by hand so they can carry target and rel attributes
+fn with_external_link_targets(events: Vec<Event<'_>>) -> Vec<Event<'_>> {
+    let mut rewritten = Vec::with_capacity(events.len());
+    let mut open_links = Vec::new();
+
+    for event in events {
+        match event {
+            Event::Start(Tag::Link {
+                ref dest_url,
+                ref title,
+                ..
+            }) if is_external(dest_url) => {
+                let title = if title.is_empty() {
+                    String::new()
+                } else {
+                    format!(" title=\"{}\"", escape_html(title))
+                };
+                rewritten.push(Event::InlineHtml(
+                    format!(
+                        "<a href=\"{}\"{} target=\"_blank\" rel=\"noopener noreferrer\">",
+                        escape_html(dest_url),
+                        title
+                    )
+                    .into(),
+                ));
+                open_links.push(true);
+            }
+            Event::Start(Tag::Link { .. }) => {
+                open_links.push(false);
+                rewritten.push(event);
+            }
+            Event::End(TagEnd::Link) => {
+                if open_links.pop().unwrap_or(false) {
+                    rewritten.push(Event::InlineHtml("</a>".into()));
+                } else {
+                    rewritten.push(event);
+                }
+            }
+            _ => rewritten.push(event),
+        }
+    }
+    rewritten
+}
+
+// Replace fenced code blocks with syntax-highlighted HTML where the language is known
+fn with_highlighted_code(events: Vec<Event<'_>>) -> Vec<Event<'_>> {
+    let mut rewritten = Vec::with_capacity(events.len());
+    let mut block: Option<(String, Vec<Event<'_>>)> = None;
+
+    for event in events {
+        match (&mut block, event) {
+            (None, Event::Start(Tag::CodeBlock(CodeBlockKind::Fenced(info)))) => {
+                let lang = info.split_whitespace().next().unwrap_or("").to_string();
+                let start = Event::Start(Tag::CodeBlock(CodeBlockKind::Fenced(info)));
+                block = Some((lang, vec![start]));
+            }
+            (Some((lang, buffered)), Event::End(TagEnd::CodeBlock)) => {
+                let code: String = buffered
+                    .iter()
+                    .filter_map(|e| match e {
+                        Event::Text(t) => Some(t.as_ref()),
+                        _ => None,
+                    })
+                    .collect();
+
+                let highlighted = if lang.is_empty() {
+                    None
+                } else {
+                    highlight(lang, &code)
+                };
+                match highlighted {
+                    Some(html) => rewritten.push(Event::Html(
+                        format!(
+                            "<pre><code class=\"language-{}\">{}</code></pre>\n",
+                            escape_html(lang),
+                            html
+                        )
+                        .into(),
+                    )),
+                    None => {
+                        rewritten.append(buffered);
+                        rewritten.push(Event::End(TagEnd::CodeBlock));
+                    }
+                }
+                block = None;
+            }
+            (Some((_, buffered)), event) => buffered.push(event),
+            (None, event) => rewritten.push(event),
+        }
+    }
+    rewritten
+}
+
+// Turn ```mermaid code blocks into <div class="mermaid"> with the escaped source,
+// which mermaid.js reads back as text
+fn with_mermaid_blocks(events: Vec<Event<'_>>) -> Vec<Event<'_>> {
+    let mut rewritten = Vec::with_capacity(events.len());
+    let mut source: Option<String> = None;
+
+    for event in events {
+        match (&mut source, event) {
+            (None, Event::Start(Tag::CodeBlock(CodeBlockKind::Fenced(info))))
+                if info.split_whitespace().next() == Some("mermaid") =>
+            {
+                source = Some(String::new());
+            }
+            (Some(code), Event::Text(text)) => code.push_str(&text),
+            (Some(code), Event::End(TagEnd::CodeBlock)) => {
+                rewritten.push(Event::Html(
+                    format!("<div class=\"mermaid\">{}</div>\n", escape_html(code)).into(),
+                ));
+                source = None;
+            }
+            (_, event) => rewritten.push(event),
+        }
+    }
+    rewritten
+}
+
+// Expand tabs to the next tab stop, tracking the column across lines
+fn expand_tabs(text: &str, width: usize, column: &mut usize) -> String {
+    let mut expanded = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '\t' => {
+                let spaces = width - *column % width;
+                expanded.extend(std::iter::repeat_n(' ', spaces));
+                *column += spaces;
+            }
+            '\n' => {
+                expanded.push(c);
+                *column = 0;
+            }
+            _ => {
+                expanded.push(c);
+                *column += 1;
+            }
+        }
+    }
+    expanded
+}
+
+// Replace tabs with spaces in code blocks only, so prose is left alone
+fn with_expanded_tabs(events: Vec<Event<'_>>, width: usize) -> Vec<Event<'_>> {
+    let mut in_code_block = false;
+    let mut column = 0;
+    events
+        .into_iter()
+        .map(|event| match event {
+            Event::Start(Tag::CodeBlock(_)) => {
+                in_code_block = true;
+                column = 0;
+                event
+            }
+            Event::End(TagEnd::CodeBlock) => {
+                in_code_block = false;
+                event
+            }
+            Event::Text(text) if in_code_block && text.contains('\t') => {
+                Event::Text(expand_tabs(&text, width, &mut column).into())
+            }
+            Event::Text(text) if in_code_block => {
+                match text.rfind('\n') {
+                    Some(i) => column = text[i + 1..].chars().count(),
+                    None => column += text.chars().count(),
+                }
+                Event::Text(text)
+            }
+            event => event,
+        })
+        .collect()
+}
+
+// Number footnotes by first reference and move every definition into an ordered list
+// at the end, with links back to each place it was referenced
+fn with_footnotes_at_end<'a>(events: Vec<Event<'a>>, heading: &str) -> Vec<Event<'a>> {
+    let mut rewritten = Vec::with_capacity(events.len());
+    let mut definitions: Vec<(String, Vec<Event<'a>>)> = Vec::new();
+    // Labels in the order they're first referenced, with how often each is referenced
+    let mut order: Vec<(String, usize)> = Vec::new();
+    // The definition being read; they can't nest
+    let mut open: Option<(String, Vec<Event<'a>>)> = None;
+
+    for event in events {
+        match event {
+            Event::Start(Tag::FootnoteDefinition(label)) => {
+                open = Some((label.to_string(), Vec::new()));
+            }
+            Event::End(TagEnd::FootnoteDefinition) => definitions.extend(open.take()),
+            Event::FootnoteReference(label) => {
+                let (number, count) = match order.iter().position(|(l, _)| **l == *label) {
+                    Some(i) => {
+                        order[i].1 += 1;
+                        (i + 1, order[i].1)
+                    }
+                    None => {
+                        order.push((label.to_string(), 1));
+                        (order.len(), 1)
+                    }
+                };
+                let id = escape_html(&label);
+                let reference = Event::InlineHtml(
+                    format!(
+                        "<sup class=\"footnote-reference\" id=\"fnref-{}-{}\"><a href=\"#fn-{}\">{}</a></sup>",
+                        id, count, id, number
+                    )
+                    .into(),
+                );
+                match open.as_mut() {
+                    Some((_, body)) => body.push(reference),
+                    None => rewritten.push(reference),
+                }
+            }
+            event => match open.as_mut() {
+                Some((_, body)) => body.push(event),
+                None => rewritten.push(event),
+            },
+        }
+    }
+
+    if definitions.is_empty() {
+        return rewritten;
+    }
+
+    // Referenced definitions in reference order, then any that were never referenced
+    definitions.sort_by_key(|(label, _)| {
+        order
+            .iter()
+            .position(|(l, _)| l == label)
+            .unwrap_or(usize::MAX)
+    });
+
+    rewritten.push(Event::Html(
+        format!(
+            "<section class=\"footnotes\">\n<h2>{}</h2>\n<ol>\n",
+            escape_html(heading)
+        )
+        .into(),
+    ));
+    for (label, mut body) in definitions {
+        let id = escape_html(&label);
+        let count = order
+            .iter()
+            .find(|(l, _)| *l == label)
+            .map_or(0, |(_, count)| *count);
+        let backrefs: String = (1..=count)
+            .map(|n| {
+                format!(
+                    " <a href=\"#fnref-{}-{}\" class=\"footnote-backref\">↩</a>",
+                    id, n
+                )
+            })
+            .collect();
+
+        rewritten.push(Event::Html(format!("<li id=\"fn-{}\">\n", id).into()));
+        // Put the back-references at the end of the last paragraph when there is one
+        let closes_paragraph = matches!(body.last(), Some(Event::End(TagEnd::Paragraph)));
+        let end = closes_paragraph.then(|| body.pop()).flatten();
+        rewritten.extend(body);
+        rewritten.push(Event::InlineHtml(backrefs.into()));
+        rewritten.extend(end);
+        rewritten.push(Event::Html("</li>\n".into()));
+    }
+    rewritten.push(Event::Html("</ol>\n</section>\n".into()));
+    rewritten
+}
+
+// Post-processing passes applied to the parser's events before rendering
+pub(crate) fn process_events<'a>(events: Vec<Event<'a>>, options: &ParseOptions) -> Vec<Event<'a>> {
+    let mut highlight_time = Duration::ZERO;
+    run_passes(events, options, &mut highlight_time)
+}
+
+// The passes themselves, adding the time spent highlighting code to `highlight_time`
+fn run_passes<'a>(
+    events: Vec<Event<'a>>,
+    options: &ParseOptions,
+    highlight_time: &mut Duration,
+) -> Vec<Event<'a>> {
+    let mut events = events;
+    if options.footnotes && options.footnotes_at_end {
+        events = with_footnotes_at_end(events, &options.footnotes_heading);
+    }
+    for stage in &options.pipeline {
+        events = match stage {
+            Stage::HeadingIds => with_heading_ids(events),
+            Stage::Mermaid if options.mermaid => with_mermaid_blocks(events),
+            Stage::Tabs if options.tab_width > 0 => {
+                with_expanded_tabs(events, options.tab_width as usize)
+            }
+            Stage::Highlight if options.highlight_code => {
+                let started = Instant::now();
+                let events = with_highlighted_code(events);
+                *highlight_time += started.elapsed();
+                events
+            }
+            Stage::ExternalLinks if options.external_links_blank => {
+                with_external_link_targets(events)
+            }
+            _ => events,
+        };
+    }
+    events
+}
+
+pub(crate) fn render_html(input: &str, options: &ParseOptions) -> String {
+    render_html_with_images(input, options, |_| None)
+}
+
+// Render like render_html, letting `image_src` swap out image destinations (None keeps them)
+pub(crate) fn render_html_with_images(
+    input: &str,
+    options: &ParseOptions,
+    image_src: impl Fn(&str) -> Option<String>,
+) -> String {
+    render_mapped(input, options, Options::empty(), |event| match event {
+        Event::Start(Tag::Image {
+            link_type,
+            dest_url,
+            title,
+            id,
+        }) => {
+            let dest_url = image_src(&dest_url).map(Into::into).unwrap_or(dest_url);
+            Event::Start(Tag::Image {
+                link_type,
+                dest_url,
+                title,
+                id,
+            })
+        }
+        event => event,
+    })
+}
+
+// The shared render path: includes, parsing with any `extra` extensions, `map` over the
+// parser's events, then the usual passes
+fn render_mapped(
+    input: &str,
+    options: &ParseOptions,
+    extra: Options,
+    map: impl FnMut(Event<'_>) -> Event<'_>,
+) -> String {
+    let expanded;
+    let input = if options.expands_includes(input) {
+        expanded = expand_includes(input);
+        expanded.as_str()
+    } else {
+        input
+    };
+
+    let events = process_events(
+        Parser::new_ext(input, options.cmark_options() | extra)
+            .map(map)
+            .collect(),
+        options,
+    );
+    let mut output = String::with_capacity(input.len() * 3 / 2);
+    html::push_html(&mut output, events.into_iter());
+    output
+}
+
+// Plain text of the first level-one heading, skipping frontmatter
+pub(crate) fn first_h1(content: &str) -> Option<String> {
+    let (_, body) = split_frontmatter(content);
+    let mut in_h1 = false;
+    let mut text = String::new();
+
+    for event in Parser::new_ext(body, Options::empty()) {
+        match event {
+            Event::Start(Tag::Heading {
+                level: HeadingLevel::H1,
+                ..
+            }) => in_h1 = true,
+            Event::End(TagEnd::Heading(HeadingLevel::H1)) => {
+                let title = text.trim();
+                if !title.is_empty() {
+                    return Some(title.to_string());
+                }
+                in_h1 = false;
+                text.clear();
+            }
+            Event::Text(t) | Event::Code(t) if in_h1 => text.push_str(&t),
+            _ => {}
+        }
+    }
+    None
+}
+
+// NUL never survives parsing, so it's safe to use as a block boundary marker
+const BLOCK_MARKER: char = '\0';
+
+fn block_map(content: &str, options: &ParseOptions) -> RenderMap {
+    let (_, body) = split_frontmatter(content);
+    let body_offset = content.len() - body.len();
+    let lines = LineIndex::new(content);
+
+    let mut events = Vec::new();
+    let mut ranges = Vec::new();
+    let mut block_start = 0;
+    let mut depth = 0;
+
+    for (event, range) in Parser::new_ext(body, options.cmark_options()).into_offset_iter() {
+        if depth == 0 {
+            block_start = range.start;
+            events.push(Event::InlineHtml(BLOCK_MARKER.to_string().into()));
+        }
+        match &event {
+            Event::Start(_) => depth += 1,
+            Event::End(_) => depth -= 1,
+            _ => {}
+        }
+        events.push(event);
+
+        // Nested blocks are folded into their outermost parent
+        if depth == 0 {
+            let end = block_start + body[block_start..range.end].trim_end().len();
+            ranges.push((body_offset + block_start, body_offset + end));
+        }
+    }
+
+    // Render in one pass so footnote numbering stays consistent across blocks
+    let mut rendered = String::with_capacity(content.len() * 3 / 2);
+    html::push_html(&mut rendered, process_events(events, options).into_iter());
+
+    let mut html_out = String::with_capacity(rendered.len());
+    let mut html_starts = Vec::with_capacity(ranges.len());
+    for (i, segment) in rendered.split(BLOCK_MARKER).enumerate() {
+        if i > 0 {
+            html_starts.push(html_out.len());
+        }
+        html_out.push_str(segment);
+    }
+    html_starts.push(html_out.len());
+
+    let blocks = ranges
+        .into_iter()
+        .enumerate()
+        .map(|(i, (start_byte, end_byte))| BlockRange {
+            start_byte,
+            end_byte,
+            start_line: lines.line(start_byte),
+            end_line: lines.line(end_byte.saturating_sub(1).max(start_byte)),
+            html_start: html_starts[i],
+            html_end: html_starts[i + 1],
+        })
+        .collect();
+
+    RenderMap {
+        html: html_out,
+        blocks,
+    }
+}
+
+// Render a note's body to HTML, using the vault's parse options unless overridden
+#[tauri::command]
+pub async fn parse_markdown(
+    input: String,
+    options: Option<ParseOptions>,
+    settings: State<'_, SettingsState>,
+    cache: State<'_, RenderCache>,
+) -> Result<String, String> {
+    let options = match options {
+        Some(options) => options,
+        None => settings.get()?.parse_options,
+    };
+
+    // Included files can change without the note changing, so those renders aren't cached
+    let cacheable = !options.expands_includes(&input);
+    let key = cache_key(&input, &options);
+    if cacheable {
+        if let Some(html) = cache.get(key) {
+            return Ok(html);
+        }
+    }
+
+    let (_, body) = split_frontmatter(&input);
+    let html = render_html(body, &options);
+    if cacheable {
+        cache.insert(key, html.clone());
+    }
+    Ok(html)
+}
+
+#[derive(Debug, Serialize)]
+pub struct MathSpan {
+    index: usize,
+    // $$...$$ rather than $...$
+    display: bool,
+    source: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct MathRender {
+    html: String,
+    math: Vec<MathSpan>,
+}
+
+// Render a note with its `$...$` / `$$...$$` math pulled out, leaving an empty
+// `<span class="math" data-math-index="N">` where each one was for a client-side
+// renderer to fill. The parser handles the math, so dollars in code are left alone
+#[tauri::command]
+pub async fn parse_markdown_with_math(
+    input: String,
+    settings: State<'_, SettingsState>,
+) -> Result<MathRender, String> {
+    let options = settings.get()?.parse_options;
+    let (_, body) = split_frontmatter(&input);
+
+    let mut math = Vec::new();
+    let html = render_mapped(body, &options, Options::ENABLE_MATH, |event| {
+        let (display, source) = match event {
+            Event::InlineMath(source) => (false, source),
+            Event::DisplayMath(source) => (true, source),
+            event => return event,
+        };
+        let index = math.len();
+        math.push(MathSpan {
+            index,
+            display,
+            source: source.to_string(),
+        });
+        let class = if display { "math math-display" } else { "math" };
+        Event::InlineHtml(
+            format!(
+                "<span class=\"{}\" data-math-index=\"{}\"></span>",
+                class, index
+            )
+            .into(),
+        )
+    });
+
+    Ok(MathRender { html, math })
+}
+
+// Microsecond timings for one render. Phases after a cache hit are zero
+#[derive(Debug, Serialize)]
+pub struct TimedParse {
+    html: String,
+    cache_hit: bool,
+    cache_lookup_us: u64,
+    // Include expansion and parsing into events
+    parse_us: u64,
+    // Post-processing passes other than highlighting
+    passes_us: u64,
+    highlight_us: u64,
+    // Writing the events out as HTML
+    render_us: u64,
+    total_us: u64,
+}
+
+fn micros(duration: Duration) -> u64 {
+    duration.as_micros().min(u64::MAX as u128) as u64
+}
+
+// parse_markdown with a breakdown of where the time went, for diagnosing slow notes.
+// Kept apart from parse_markdown so the normal path doesn't pay for the bookkeeping
+#[tauri::command]
+pub async fn parse_markdown_timed(
+    input: String,
+    settings: State<'_, SettingsState>,
+    cache: State<'_, RenderCache>,
+) -> Result<TimedParse, String> {
+    let options = settings.get()?.parse_options;
+    let started = Instant::now();
+
+    let cacheable = !options.expands_includes(&input);
+    let key = cache_key(&input, &options);
+    let cached = if cacheable { cache.get(key) } else { None };
+    let cache_lookup = started.elapsed();
+    if let Some(html) = cached {
+        return Ok(TimedParse {
+            html,
+            cache_hit: true,
+            cache_lookup_us: micros(cache_lookup),
+            parse_us: 0,
+            passes_us: 0,
+            highlight_us: 0,
+            render_us: 0,
+            total_us: micros(started.elapsed()),
+        });
+    }
+
+    let phase = Instant::now();
+    let (_, body) = split_frontmatter(&input);
+    let expanded;
+    let body = if options.expands_includes(body) {
+        expanded = expand_includes(body);
+        expanded.as_str()
+    } else {
+        body
+    };
+    let events: Vec<Event> = Parser::new_ext(body, options.cmark_options()).collect();
+    let parse = phase.elapsed();
+
+    let phase = Instant::now();
+    let mut highlight = Duration::ZERO;
+    let events = run_passes(events, &options, &mut highlight);
+    let passes = phase.elapsed().saturating_sub(highlight);
+
+    let phase = Instant::now();
+    let mut html = String::with_capacity(body.len() * 3 / 2);
+    html::push_html(&mut html, events.into_iter());
+    let render = phase.elapsed();
+
+    if cacheable {
+        cache.insert(key, html.clone());
+    }
+    Ok(TimedParse {
+        html,
+        cache_hit: false,
+        cache_lookup_us: micros(cache_lookup),
+        parse_us: micros(parse),
+        passes_us: micros(passes),
+        highlight_us: micros(highlight),
+        render_us: micros(render),
+        total_us: micros(started.elapsed()),
+    })
+}
+
+// Render along with the source range of every top-level block, for cursor-follow preview
+#[tauri::command]
+pub async fn render_with_map(
+    content: String,
+    settings: State<'_, SettingsState>,
+) -> Result<RenderMap, String> {
+    let options = settings.get()?.parse_options;
+    Ok(block_map(&content, &options))
+}
+
+// Render a single heading's section, up to the next heading of the same or higher level
+#[tauri::command]
+pub async fn render_section(
+    content: String,
+    slug: String,
+    settings: State<'_, SettingsState>,
+) -> Result<String, String> {
+    let options = settings.get()?.parse_options;
+    section_html(&content, &slug, &options)
+}
+
+// One section rendered on its own, with heading ids taken from the whole document so
+// repeated headings keep the suffixes (intro-1, ...) they get in the full render
+fn section_html(content: &str, slug: &str, options: &ParseOptions) -> Result<String, String> {
+    let headings = headings(content);
+    let index = headings
+        .iter()
+        .position(|h| h.slug == slug)
+        .ok_or_else(|| format!("Section not found: {}", slug))?;
+
+    let heading = &headings[index];
+    let end = headings[index + 1..]
+        .iter()
+        .find(|h| h.level <= heading.level)
+        .map(|h| h.start)
+        .unwrap_or(content.len());
+
+    let mut slugs = headings[index..]
+        .iter()
+        .take_while(|h| h.start < end)
+        .map(|h| h.slug.clone());
+    Ok(render_mapped(
+        &content[heading.start..end],
+        options,
+        Options::empty(),
+        |event| match event {
+            Event::Start(Tag::Heading {
+                level,
+                id: None,
+                classes,
+                attrs,
+            }) => Event::Start(Tag::Heading {
+                level,
+                id: slugs.next().map(Into::into),
+                classes,
+                attrs,
+            }),
+            event => event,
+        },
+    ))
+}
+
+// Where a `note.md#anchor` link lands: the heading with that id, if there is one
+#[derive(Debug, Serialize)]
+pub struct AnchorInfo {
+    exists: bool,
+    slug: String,
+    line: Option<usize>,
+    heading: Option<String>,
+}
+
+// Find the heading an anchor points at, matching the ids headings get when rendered.
+// Anchors that aren't in slug form ("#Some Section") are slugified before giving up
+#[tauri::command]
+pub async fn resolve_anchor(path: String, anchor: String) -> Result<AnchorInfo, String> {
+    let file_path = resolve_in_vault(&path)?;
+    let content =
+        fs::read_to_string(&file_path).map_err(|e| format!("Failed to read file: {}", e))?;
+    let anchor = percent_decode(anchor.trim().trim_start_matches('#'));
+
+    let headings = headings(&content);
+    let found = headings
+        .iter()
+        .find(|h| h.slug == anchor)
+        .or_else(|| headings.iter().find(|h| h.slug == slugify(&anchor)));
+
+    Ok(match found {
+        Some(heading) => AnchorInfo {
+            exists: true,
+            slug: heading.slug.clone(),
+            line: Some(heading.line),
+            heading: Some(heading.text.clone()),
+        },
+        None => AnchorInfo {
+            exists: false,
+            slug: anchor,
+            line: None,
+            heading: None,
+        },
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn external_links_open_in_a_new_window() {
+        let html = render_html(
+            "[site](https://example.com) and [note](other.md)",
+            &ParseOptions::default(),
+        );
+
+        assert!(html.contains(
+            r#"<a href="https://example.com" target="_blank" rel="noopener noreferrer">site</a>"#
+        ));
+        assert!(html.contains(r#"<a href="other.md">note</a>"#));
+    }
+
+    #[test]
+    fn smart_punctuation_leaves_code_alone() {
+        let options = ParseOptions {
+            smart_punctuation: true,
+            ..ParseOptions::default()
+        };
+        let html = render_html(
+            "He said \"hi\" -- `\"raw\" --`\n\n```\n\"fenced\" --\n```\n",
+            &options,
+        );
+
+        assert!(html.contains("He said \u{201c}hi\u{201d} \u{2013}"));
+        assert!(html.contains("<code>\"raw\" --</code>"));
+        assert!(html.contains("<pre><code>\"fenced\" --\n</code></pre>"));
+    }
+
+    fn footnotes_at_end() -> ParseOptions {
+        ParseOptions {
+            footnotes_at_end: true,
+            ..ParseOptions::default()
+        }
+    }
+
+    #[test]
+    fn footnotes_are_numbered_by_first_reference() {
+        let html = render_html(
+            "First[^b] then[^a].\n\n[^a]: Note A.\n\n[^b]: Note B.\n",
+            &footnotes_at_end(),
+        );
+
+        assert!(html.contains(r##"id="fnref-b-1"><a href="#fn-b">1</a>"##));
+        assert!(html.contains(r##"id="fnref-a-1"><a href="#fn-a">2</a>"##));
+        let b = html.find(r#"<li id="fn-b">"#).unwrap();
+        let a = html.find(r#"<li id="fn-a">"#).unwrap();
+        assert!(b < a);
+        assert!(html.find(r#"<section class="footnotes">"#).unwrap() < b);
+    }
+
+    #[test]
+    fn repeated_references_each_get_a_backref() {
+        let html = render_html(
+            "One[^n] and again[^n].\n\n[^n]: Shared.\n",
+            &footnotes_at_end(),
+        );
+
+        assert!(html.contains(r##"id="fnref-n-1"><a href="#fn-n">1</a>"##));
+        assert!(html.contains(r##"id="fnref-n-2"><a href="#fn-n">1</a>"##));
+        assert!(html.contains(concat!(
+            r##"Shared. <a href="#fnref-n-1" class="footnote-backref">↩</a>"##,
+            r##" <a href="#fnref-n-2" class="footnote-backref">↩</a></p>"##
+        )));
+    }
+
+    #[test]
+    fn sections_keep_the_full_render_heading_ids() {
+        let content = "# Intro\n\n## Setup\n\n# Intro\n\n## Setup\n\nText\n";
+        let options = ParseOptions::default();
+        let full = render_html(content, &options);
+        let section = section_html(content, "intro-1", &options).unwrap();
+
+        assert!(full.contains(r#"<h1 id="intro-1">Intro</h1>"#));
+        assert!(section.contains(r#"<h1 id="intro-1">Intro</h1>"#));
+        assert!(section.contains(r#"<h2 id="setup-1">Setup</h2>"#));
+        assert!(!section.contains(r#"id="intro""#));
+    }
+}
@@ -0,0 +1,43 @@
+use serde::{Deserialize, Serialize};
+use std::sync::atomic::{AtomicBool, Ordering};
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum AppMode {
+    Read,
+    Edit,
+}
+
+// Set while the app is in read mode. Only kept for the session, so every launch
+// starts out editable; a static so any write path can check it without extra state
+static READ_MODE: AtomicBool = AtomicBool::new(false);
+
+pub(crate) fn is_read_mode() -> bool {
+    READ_MODE.load(Ordering::Relaxed)
+}
+
+// Call before anything that changes the vault
+pub(crate) fn ensure_editable() -> Result<(), String> {
+    if is_read_mode() {
+        return Err("Margherita is in read mode".to_string());
+    }
+    Ok(())
+}
+
+// Switch between reading and editing. In read mode every command that would change a
+// note fails, whatever the note's own lock says
+#[tauri::command]
+pub async fn set_mode(mode: AppMode) -> Result<(), String> {
+    READ_MODE.store(mode == AppMode::Read, Ordering::Relaxed);
+    println!("Switched to {:?} mode", mode);
+    Ok(())
+}
+
+#[tauri::command]
+pub async fn get_mode() -> Result<AppMode, String> {
+    Ok(if is_read_mode() {
+        AppMode::Read
+    } else {
+        AppMode::Edit
+    })
+}
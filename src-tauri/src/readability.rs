@@ -0,0 +1,105 @@
+use serde::Serialize;
+
+use crate::text::plain_text;
+
+#[derive(Debug, Serialize)]
+pub struct Readability {
+    sentences: usize,
+    words: usize,
+    syllables: usize,
+    flesch_reading_ease: f64,
+    flesch_kincaid_grade: f64,
+    label: String,
+}
+
+// Vowel groups, less a silent final "e" ("make" but not "table"); every word has at least one
+fn syllables(word: &str) -> usize {
+    let word: Vec<char> = word
+        .chars()
+        .filter(|c| c.is_alphabetic())
+        .flat_map(char::to_lowercase)
+        .collect();
+    let is_vowel = |c: char| matches!(c, 'a' | 'e' | 'i' | 'o' | 'u' | 'y');
+
+    let mut count = 0;
+    let mut previous_vowel = false;
+    for &c in &word {
+        let vowel = is_vowel(c);
+        if vowel && !previous_vowel {
+            count += 1;
+        }
+        previous_vowel = vowel;
+    }
+
+    if let [.., before, 'l', 'e'] = word.as_slice() {
+        if is_vowel(*before) {
+            count -= 1;
+        }
+    } else if let [.., before, 'e'] = word.as_slice() {
+        if !is_vowel(*before) && count > 1 {
+            count -= 1;
+        }
+    }
+    count.max(1)
+}
+
+// Sentences end at . ! ? and at the end of each paragraph, heading or list item
+fn sentence_count(text: &str) -> usize {
+    text.lines()
+        .map(|line| {
+            line.split(['.', '!', '?'])
+                .filter(|part| part.chars().any(char::is_alphanumeric))
+                .count()
+        })
+        .sum()
+}
+
+fn label(ease: f64) -> &'static str {
+    match ease {
+        e if e >= 90.0 => "Very easy",
+        e if e >= 80.0 => "Easy",
+        e if e >= 70.0 => "Fairly easy",
+        e if e >= 60.0 => "Standard",
+        e if e >= 50.0 => "Fairly difficult",
+        e if e >= 30.0 => "Difficult",
+        _ => "Very difficult",
+    }
+}
+
+// Flesch Reading Ease and Flesch-Kincaid grade for a note's prose, with code,
+// markup and frontmatter left out. Syllables are estimated from the spelling
+#[tauri::command]
+pub async fn readability(content: String) -> Result<Readability, String> {
+    let text = plain_text(&content);
+    let words: Vec<&str> = text
+        .split_whitespace()
+        .filter(|w| w.chars().any(char::is_alphabetic))
+        .collect();
+    let sentences = sentence_count(&text);
+    let syllables: usize = words.iter().map(|w| syllables(w)).sum();
+
+    if words.is_empty() || sentences == 0 {
+        return Ok(Readability {
+            sentences,
+            words: words.len(),
+            syllables,
+            flesch_reading_ease: 0.0,
+            flesch_kincaid_grade: 0.0,
+            label: "Not enough text".to_string(),
+        });
+    }
+
+    let words_per_sentence = words.len() as f64 / sentences as f64;
+    let syllables_per_word = syllables as f64 / words.len() as f64;
+    let ease = 206.835 - 1.015 * words_per_sentence - 84.6 * syllables_per_word;
+    let grade = 0.39 * words_per_sentence + 11.8 * syllables_per_word - 15.59;
+
+    Ok(Readability {
+        sentences,
+        words: words.len(),
+        syllables,
+        flesch_reading_ease: ease,
+        flesch_kincaid_grade: grade,
+        label: label(ease).to_string(),
+    })
+}
@@ -0,0 +1,540 @@
+use serde::Serialize;
+use std::collections::HashSet;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::get_margherita_dir;
+use crate::links::{rebase_links, rewrite_for_folder_move, rewrite_links};
+use crate::lock::{ensure_unlocked, locked_notes};
+use crate::mode::ensure_editable;
+use crate::slug::slugify;
+use crate::title::note_title;
+use crate::vault::{
+    active_notes, collect_notes, ensure_inside_vault, is_note, parent_of, relative_path,
+    resolve_in_vault,
+};
+use crate::wikilink::WikiResolver;
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RenameStatus {
+    Ok,
+    MissingSource,
+    TargetExists,
+    DuplicateTarget,
+    OutsideVault,
+    Locked,
+}
+
+#[derive(Debug, Serialize)]
+pub struct RenamePreview {
+    old: String,
+    new: String,
+    status: RenameStatus,
+}
+
+#[derive(Debug, Serialize)]
+pub struct RenameReport {
+    path: String,
+    // Notes whose links were rewritten, the renamed note included when it changed
+    updated: Vec<String>,
+    // Notes that link to the old name but are locked, so were left as they were
+    locked: Vec<String>,
+}
+
+// A rewritten note waiting in a temp file next to it, with what it held before
+struct Staged {
+    path: PathBuf,
+    temp: PathBuf,
+    original: String,
+}
+
+fn stage(path: &Path, content: &str, original: String) -> Result<Staged, String> {
+    let file_name = path
+        .file_name()
+        .map(|n| n.to_string_lossy().into_owned())
+        .ok_or_else(|| format!("Invalid file path: {:?}", path))?;
+    let temp = path.with_file_name(format!(".{}.tmp", file_name));
+    fs::write(&temp, content).map_err(|e| {
+        let _ = fs::remove_file(&temp);
+        format!("Failed to write file: {}", e)
+    })?;
+    Ok(Staged {
+        path: path.to_path_buf(),
+        temp,
+        original,
+    })
+}
+
+// Stage every (path, new content, old content) change, or none of them
+fn stage_all(changes: Vec<(PathBuf, String, String)>) -> Result<Vec<Staged>, String> {
+    let mut staged = Vec::with_capacity(changes.len());
+    for (path, content, original) in changes {
+        match stage(&path, &content, original) {
+            Ok(s) => staged.push(s),
+            Err(e) => {
+                discard(&staged);
+                return Err(e);
+            }
+        }
+    }
+    Ok(staged)
+}
+
+fn discard(staged: &[Staged]) {
+    for s in staged {
+        let _ = fs::remove_file(&s.temp);
+    }
+}
+
+// Put back notes that were already swapped in, newest first
+fn restore(committed: &[Staged]) {
+    for s in committed.iter().rev() {
+        if let Err(e) = fs::write(&s.path, &s.original) {
+            println!("Failed to restore {:?}: {}", s.path, e);
+        }
+    }
+}
+
+// Swap every staged note in; on failure the ones already swapped are put back
+fn commit(staged: &[Staged]) -> Result<(), String> {
+    for (i, s) in staged.iter().enumerate() {
+        if let Err(e) = fs::rename(&s.temp, &s.path) {
+            discard(&staged[i..]);
+            restore(&staged[..i]);
+            return Err(format!("Failed to update links: {}", e));
+        }
+    }
+    Ok(())
+}
+
+// Create the missing folders above `path`, returning them deepest first so a
+// failed move can take them away again
+fn create_parents(path: &Path) -> Result<Vec<PathBuf>, String> {
+    let created: Vec<PathBuf> = path
+        .ancestors()
+        .skip(1)
+        .take_while(|p| !p.exists())
+        .map(Path::to_path_buf)
+        .collect();
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent).map_err(|e| format!("Failed to create directory: {}", e))?;
+    }
+    Ok(created)
+}
+
+fn remove_created(created: &[PathBuf]) {
+    for dir in created {
+        if let Err(e) = fs::remove_dir(dir) {
+            println!("Failed to remove {:?}: {}", dir, e);
+        }
+    }
+}
+
+// Rename a note and rewrite links to it in every other note. `own` replaces the
+// renamed note's content, e.g. with its relative links rebased. Every rewrite is
+// staged in a temp file before anything moves, and a failure part way through puts
+// the note and the notes already updated back. Locked notes are left alone and reported
+pub(crate) fn rename_with_links(
+    dir: &Path,
+    old: &str,
+    new: &str,
+    own: Option<String>,
+) -> Result<RenameReport, String> {
+    ensure_editable()?;
+    let source = resolve_in_vault(old)?;
+    let target = resolve_in_vault(new)?;
+
+    if !source.is_file() {
+        return Err(format!("File not found: {}", old));
+    }
+    if target.exists() {
+        return Err(format!("A file named {} already exists", new));
+    }
+
+    let old = relative_path(dir, &source);
+    let new = relative_path(dir, &target);
+    let locked = locked_notes()?;
+    if locked.contains(&old) {
+        return Err(format!("Note is locked: {}", old));
+    }
+
+    // A symlinked note moves as a link, so its content stays where the link points
+    let moved_to = if source.is_symlink() {
+        ensure_inside_vault(&source)?
+    } else {
+        ensure_inside_vault(&target)?
+    };
+    let original =
+        fs::read_to_string(&source).map_err(|e| format!("Failed to read file: {}", e))?;
+    let before = WikiResolver::build(dir)?;
+    let after = before.renamed(&old, &new);
+    let moved = rewrite_links(
+        own.as_deref().unwrap_or(&original),
+        &new,
+        &old,
+        &new,
+        &before,
+        &after,
+    );
+
+    let mut report = RenameReport {
+        path: new.clone(),
+        updated: Vec::new(),
+        locked: Vec::new(),
+    };
+    let mut changes = Vec::new();
+    if moved != original {
+        changes.push((moved_to, moved, original));
+        report.updated.push(new.clone());
+    }
+
+    for path in collect_notes(dir)? {
+        if path == source {
+            continue;
+        }
+        let note = relative_path(dir, &path);
+        let content = match fs::read_to_string(&path) {
+            Ok(content) => content,
+            Err(e) => {
+                println!("Skipping {:?} while updating links: {}", path, e);
+                continue;
+            }
+        };
+
+        let rewritten = rewrite_links(&content, &note, &old, &new, &before, &after);
+        if rewritten == content {
+            continue;
+        }
+        if locked.contains(&note) {
+            report.locked.push(note);
+            continue;
+        }
+        changes.push((ensure_inside_vault(&path)?, rewritten, content));
+        report.updated.push(note);
+    }
+
+    // The temp file for the moved note sits in its new folder, so that has to exist first
+    let created = create_parents(&target)?;
+    let staged = match stage_all(changes) {
+        Ok(staged) => staged,
+        Err(e) => {
+            remove_created(&created);
+            return Err(e);
+        }
+    };
+
+    if let Err(e) = fs::rename(&source, &target) {
+        discard(&staged);
+        remove_created(&created);
+        return Err(format!("Failed to rename file: {}", e));
+    }
+    println!("Renamed {:?} to {:?}", source, target);
+
+    if let Err(e) = commit(&staged) {
+        if let Err(e) = fs::rename(&target, &source) {
+            println!("Failed to move {:?} back: {}", target, e);
+        }
+        remove_created(&created);
+        return Err(format!("{}, rename undone", e));
+    }
+
+    println!(
+        "Updated links in {} notes, skipped {} locked",
+        report.updated.len(),
+        report.locked.len()
+    );
+    Ok(report)
+}
+
+// Propose (and unless dry_run, perform) renames so each file is named after its title
+#[tauri::command]
+pub async fn sync_filenames_to_titles(dry_run: bool) -> Result<Vec<(String, String)>, String> {
+    let dir = get_margherita_dir()?;
+    let locked = locked_notes()?;
+    let mut claimed = HashSet::new();
+    let mut renames = Vec::new();
+
+    for path in active_notes(&dir)? {
+        if locked.contains(&relative_path(&dir, &path)) {
+            continue;
+        }
+        let content = match fs::read_to_string(&path) {
+            Ok(content) => content,
+            Err(_) => continue,
+        };
+
+        let slug = match note_title(&content).map(|t| slugify(&t)) {
+            Some(slug) if !slug.is_empty() => slug,
+            _ => continue,
+        };
+
+        let extension = path
+            .extension()
+            .map(|e| e.to_string_lossy().into_owned())
+            .unwrap_or_else(|| "md".to_string());
+        let target = path.with_file_name(format!("{}.{}", slug, extension));
+
+        // Skip notes already in sync, and never clobber or double-book a name
+        if target == path || target.exists() || !claimed.insert(target.clone()) {
+            continue;
+        }
+
+        renames.push((relative_path(&dir, &path), relative_path(&dir, &target)));
+    }
+
+    if !dry_run {
+        for (old, new) in &renames {
+            rename_with_links(&dir, old, new, None)?;
+        }
+    }
+
+    Ok(renames)
+}
+
+// Move a note and update links to it. Unless `rewrite_links` is false, the note's own
+// relative links are fixed up to still resolve
+fn move_with_links(from: &str, to: &str, rewrite_links: bool) -> Result<RenameReport, String> {
+    let dir = get_margherita_dir()?;
+    let source = resolve_in_vault(from)?;
+    let target = resolve_in_vault(to)?;
+    if !is_note(&target) {
+        return Err(format!("Not a note path: {}", to));
+    }
+    let old = relative_path(&dir, &source);
+    let new = relative_path(&dir, &target);
+    ensure_unlocked(&old)?;
+
+    // Work out the rebased links before the move, while the old layout is on disk
+    let rebased = if rewrite_links {
+        let content =
+            fs::read_to_string(&source).map_err(|e| format!("Failed to read file: {}", e))?;
+        let rebased = rebase_links(&content, &old, &new, |p| dir.join(p).exists());
+        (rebased != content).then_some(rebased)
+    } else {
+        None
+    };
+
+    rename_with_links(&dir, &old, &new, rebased)
+}
+
+// Move a note to another path, updating links to it across the vault. Unless
+// `rewrite_links` is false, the note's own relative links are fixed up to still resolve
+#[tauri::command]
+pub async fn move_file(
+    from: String,
+    to: String,
+    rewrite_links: Option<bool>,
+) -> Result<String, String> {
+    move_with_links(&from, &to, rewrite_links.unwrap_or(true)).map(|report| report.path)
+}
+
+// Rename a note in place, keeping it in its folder. `new_name` is a bare file name;
+// without a note extension the old one is kept. Links are updated like move_file, and
+// locked notes that still link to the old name are reported
+#[tauri::command]
+pub async fn rename_file(path: String, new_name: String) -> Result<RenameReport, String> {
+    let new_name = new_name.trim();
+    if new_name.is_empty() || new_name.contains(['/', '\\']) {
+        return Err(format!("Invalid file name: {}", new_name));
+    }
+
+    let dir = get_margherita_dir()?;
+    let old = relative_path(&dir, &resolve_in_vault(&path)?);
+    let name = if is_note(Path::new(new_name)) {
+        new_name.to_string()
+    } else {
+        let ext = Path::new(&old)
+            .extension()
+            .map(|e| e.to_string_lossy().into_owned())
+            .unwrap_or_else(|| "md".to_string());
+        format!("{}.{}", new_name, ext)
+    };
+    let new = match parent_of(&old) {
+        "" => name,
+        parent => format!("{}/{}", parent, name),
+    };
+
+    move_with_links(&old, &new, true)
+}
+
+// Rename a folder inside the vault. With `update_links`, links into the folder
+// (and relative links out of notes inside it) are rewritten to match. Locked notes
+// are left alone and returned, and a failed rewrite moves the folder back
+#[tauri::command]
+pub async fn rename_folder(
+    old: String,
+    new: String,
+    update_links: bool,
+) -> Result<Vec<String>, String> {
+    ensure_editable()?;
+    let dir = get_margherita_dir()?;
+    let source = resolve_in_vault(&old)?;
+    let target = resolve_in_vault(&new)?;
+    let old = relative_path(&dir, &source);
+    let new = relative_path(&dir, &target);
+
+    if old.is_empty() || new.is_empty() {
+        return Err("The vault itself can't be renamed".to_string());
+    }
+    if !source.is_dir() {
+        return Err(format!("Folder not found: {}", old));
+    }
+    if target.exists() {
+        return Err(format!("A file or folder named {} already exists", new));
+    }
+    if target.starts_with(&source) {
+        return Err(format!("Can't move {} into itself", old));
+    }
+    let locked = locked_notes()?;
+    if let Some(note) = locked.iter().find(|n| n.starts_with(&format!("{}/", old))) {
+        return Err(format!("Note is locked: {}", note));
+    }
+
+    // Work out every rewrite while the old layout is on disk, checking targets
+    // against where they'll be once the folder has moved
+    let mut changes = Vec::new();
+    let mut skipped = Vec::new();
+    if update_links {
+        let exists = |p: &str| {
+            let before = match p.strip_prefix(&new) {
+                Some("") => old.clone(),
+                Some(rest) if rest.starts_with('/') => format!("{}{}", old, rest),
+                _ => p.to_string(),
+            };
+            dir.join(before).exists()
+        };
+        for path in collect_notes(&dir)? {
+            let before = relative_path(&dir, &path);
+            let now = match before.strip_prefix(&format!("{}/", old)) {
+                Some(rest) => format!("{}/{}", new, rest),
+                None => before.clone(),
+            };
+            let Ok(content) = fs::read_to_string(&path) else {
+                println!("Skipping {:?} while updating links", path);
+                continue;
+            };
+
+            let rewritten = rewrite_for_folder_move(&content, &before, &now, &old, &new, exists);
+            if rewritten == content {
+                continue;
+            }
+            if locked.contains(&before) {
+                skipped.push(before);
+                continue;
+            }
+            changes.push((now, rewritten, content));
+        }
+    }
+
+    let created = create_parents(&target)?;
+    if let Err(e) = fs::rename(&source, &target) {
+        remove_created(&created);
+        return Err(format!("Failed to rename folder: {}", e));
+    }
+    println!("Renamed folder {:?} to {:?}", source, target);
+
+    let updated = changes.len();
+    let staged = changes
+        .into_iter()
+        .map(|(now, content, original)| {
+            Ok((ensure_inside_vault(&dir.join(now))?, content, original))
+        })
+        .collect::<Result<Vec<_>, String>>()
+        .and_then(stage_all)
+        .and_then(|staged| commit(&staged));
+    if let Err(e) = staged {
+        if let Err(e) = fs::rename(&target, &source) {
+            println!("Failed to move {:?} back: {}", target, e);
+        }
+        remove_created(&created);
+        return Err(format!("{}, rename undone", e));
+    }
+
+    println!(
+        "Updated links in {} notes, skipped {} locked",
+        updated,
+        skipped.len()
+    );
+    Ok(skipped)
+}
+
+// Check a batch of renames without touching anything, so the UI can show conflicts
+// and only go ahead with the pairs that would succeed
+#[tauri::command]
+pub async fn preview_rename(pairs: Vec<(String, String)>) -> Result<Vec<RenamePreview>, String> {
+    let dir = get_margherita_dir()?;
+    let locked = locked_notes()?;
+    let mut claimed = HashSet::new();
+    let mut previews = Vec::new();
+
+    for (old, new) in pairs {
+        let status = match (resolve_in_vault(&old), resolve_in_vault(&new)) {
+            (Err(_), _) | (_, Err(_)) => RenameStatus::OutsideVault,
+            (Ok(source), Ok(target)) => {
+                if !source.is_file() {
+                    RenameStatus::MissingSource
+                } else if locked.contains(&relative_path(&dir, &source)) {
+                    RenameStatus::Locked
+                } else if target.exists() {
+                    RenameStatus::TargetExists
+                } else if !claimed.insert(target) {
+                    RenameStatus::DuplicateTarget
+                } else {
+                    RenameStatus::Ok
+                }
+            }
+        };
+        previews.push(RenamePreview { old, new, status });
+    }
+
+    Ok(previews)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // A throwaway vault under a temp HOME, shared by the tests here
+    fn vault() -> PathBuf {
+        static HOME: std::sync::OnceLock<PathBuf> = std::sync::OnceLock::new();
+        let home = HOME.get_or_init(|| {
+            let home =
+                std::env::temp_dir().join(format!("margherita-rename-{}", std::process::id()));
+            let _ = fs::remove_dir_all(&home);
+            fs::create_dir_all(home.join(".config")).unwrap();
+            fs::write(
+                home.join(".config/user-dirs.dirs"),
+                "XDG_DOCUMENTS_DIR=\"$HOME/Documents\"\n",
+            )
+            .unwrap();
+            std::env::set_var("HOME", &home);
+            std::env::remove_var("XDG_CONFIG_HOME");
+            home
+        });
+        let dir = home.join("Documents/margherita");
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    #[test]
+    fn move_into_new_folder_rebases_links() {
+        let dir = vault();
+        fs::write(dir.join("moving.md"), "See [other](other.md)\n").unwrap();
+        fs::write(dir.join("other.md"), "Other\n").unwrap();
+
+        let moved = tauri::async_runtime::block_on(move_file(
+            "moving.md".to_string(),
+            "new-folder/moving.md".to_string(),
+            None,
+        ))
+        .unwrap();
+
+        assert_eq!(moved, "new-folder/moving.md");
+        assert!(!dir.join("moving.md").exists());
+        assert_eq!(
+            fs::read_to_string(dir.join("new-folder/moving.md")).unwrap(),
+            "See [other](../other.md)\n"
+        );
+    }
+}
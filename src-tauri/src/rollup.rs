@@ -0,0 +1,196 @@
+use chrono::{DateTime, Datelike, Duration, Local, NaiveDate, Weekday};
+use serde::Deserialize;
+use std::fs;
+
+use tauri::State;
+
+use crate::extract::tasks;
+use crate::get_margherita_dir;
+use crate::index::{escape_link_text, with_generated_section};
+use crate::links::percent_encode_path;
+use crate::lock::ensure_unlocked;
+use crate::settings::SettingsState;
+use crate::text::plain_text;
+use crate::timeline::note_date;
+use crate::title::note_title;
+use crate::vault::{active_notes, relative_from, relative_path, write_atomic};
+
+// Rollups are written here, one note per period, e.g. rollups/2024-W23.md
+const ROLLUP_DIR: &str = "rollups";
+
+const ROLLUP_START: &str = "<!-- margherita:rollup:start -->";
+const ROLLUP_END: &str = "<!-- margherita:rollup:end -->";
+
+// Excerpts are cut to this many characters
+const EXCERPT_CHARS: usize = 160;
+
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Period {
+    Week,
+    LastWeek,
+    Month,
+    LastMonth,
+}
+
+impl Period {
+    // First and last day of the period around `today`, and the rollup's name
+    fn bounds(self, today: NaiveDate) -> Option<(NaiveDate, NaiveDate, String)> {
+        match self {
+            Period::Week | Period::LastWeek => {
+                let day = if self == Period::LastWeek {
+                    today - Duration::days(7)
+                } else {
+                    today
+                };
+                let week = day.iso_week();
+                let start = NaiveDate::from_isoywd_opt(week.year(), week.week(), Weekday::Mon)?;
+                let name = format!("{:04}-W{:02}", week.year(), week.week());
+                Some((start, start + Duration::days(6), name))
+            }
+            Period::Month | Period::LastMonth => {
+                let this_month = today.with_day(1)?;
+                let start = if self == Period::LastMonth {
+                    (this_month - Duration::days(1)).with_day(1)?
+                } else {
+                    this_month
+                };
+                let next = (start + Duration::days(31)).with_day(1)?;
+                Some((
+                    start,
+                    next - Duration::days(1),
+                    start.format("%Y-%m").to_string(),
+                ))
+            }
+        }
+    }
+}
+
+// A note that belongs in the rollup
+struct RollupEntry {
+    path: String,
+    title: String,
+    excerpt: String,
+    open_tasks: usize,
+    created: bool,
+}
+
+// The first line of prose that isn't the title, shortened to EXCERPT_CHARS
+fn excerpt(content: &str, title: &str) -> String {
+    let text = plain_text(content);
+    let line = text
+        .lines()
+        .map(str::trim)
+        .find(|l| !l.is_empty() && *l != title)
+        .unwrap_or_default();
+    if line.chars().count() <= EXCERPT_CHARS {
+        return line.to_string();
+    }
+    let cut: String = line.chars().take(EXCERPT_CHARS).collect();
+    format!("{}…", cut.trim_end())
+}
+
+fn rollup_section(entries: &[RollupEntry], rollup: &str) -> String {
+    let mut section = String::new();
+    for (heading, created) in [("Created", true), ("Updated", false)] {
+        let group: Vec<&RollupEntry> = entries.iter().filter(|e| e.created == created).collect();
+        if group.is_empty() {
+            continue;
+        }
+        section.push_str(&format!("\n## {}\n\n", heading));
+        for entry in group {
+            let link = percent_encode_path(&relative_from(ROLLUP_DIR, &entry.path));
+            section.push_str(&format!("- [{}]({})", escape_link_text(&entry.title), link));
+            if entry.open_tasks > 0 {
+                let plural = if entry.open_tasks == 1 { "" } else { "s" };
+                section.push_str(&format!(" ({} open task{})", entry.open_tasks, plural));
+            }
+            if !entry.excerpt.is_empty() {
+                section.push_str(&format!(": {}", entry.excerpt));
+            }
+            section.push('\n');
+        }
+    }
+    if section.is_empty() {
+        section.push_str(&format!("\nNothing was written during {}.\n", rollup));
+    }
+    section
+}
+
+// Write (or refresh) the rollup note for a period, listing the notes dated or modified
+// in it with an excerpt and their open tasks. Anything written outside the generated
+// part of the note is kept. Returns the rollup's path
+#[tauri::command]
+pub async fn build_rollup(
+    period: Period,
+    settings: State<'_, SettingsState>,
+) -> Result<String, String> {
+    let settings = settings.get()?;
+    let dir = get_margherita_dir()?;
+    let (start, end, name) = period
+        .bounds(Local::now().date_naive())
+        .ok_or_else(|| "Failed to work out the period's dates".to_string())?;
+    let rollup = format!("{}/{}.{}", ROLLUP_DIR, name, settings.default_extension);
+    let rollups = format!("{}/", ROLLUP_DIR);
+    let in_period = |date: NaiveDate| start <= date && date <= end;
+
+    let mut entries = Vec::new();
+    for path in active_notes(&dir)? {
+        let relative = relative_path(&dir, &path);
+        if relative.starts_with(&rollups) {
+            continue;
+        }
+        let created = note_date(&path).is_some_and(in_period);
+        let modified = fs::metadata(&path)
+            .and_then(|m| m.modified())
+            .map(|t| DateTime::<Local>::from(t).date_naive())
+            .is_ok_and(in_period);
+        if !created && !modified {
+            continue;
+        }
+
+        let Ok(content) = fs::read_to_string(&path) else {
+            continue;
+        };
+        let title = note_title(&content).unwrap_or_else(|| {
+            path.file_stem()
+                .map(|s| s.to_string_lossy().into_owned())
+                .unwrap_or_default()
+        });
+        entries.push(RollupEntry {
+            excerpt: excerpt(&content, &title),
+            open_tasks: tasks(&content).iter().filter(|t| !t.checked).count(),
+            path: relative,
+            title,
+            created,
+        });
+    }
+    entries.sort_by_cached_key(|e| e.path.to_lowercase());
+
+    let rollup_path = dir.join(&rollup);
+    let existing = if rollup_path.exists() {
+        ensure_unlocked(&rollup)?;
+        Some(
+            fs::read_to_string(&rollup_path)
+                .map_err(|e| format!("Failed to read rollup: {}", e))?,
+        )
+    } else {
+        None
+    };
+
+    let content = with_generated_section(
+        existing.as_deref(),
+        &name,
+        (ROLLUP_START, ROLLUP_END),
+        &rollup_section(&entries, &name),
+    );
+    if existing.as_deref() != Some(content.as_str()) {
+        if let Some(parent) = rollup_path.parent() {
+            fs::create_dir_all(parent)
+                .map_err(|e| format!("Failed to create rollups folder: {}", e))?;
+        }
+        write_atomic(&rollup_path, &content)?;
+    }
+    println!("Rolled up {} notes into {}", entries.len(), rollup);
+    Ok(rollup)
+}
@@ -0,0 +1,232 @@
+use pulldown_cmark::{CodeBlockKind, Event, Options, Parser, Tag, TagEnd};
+use std::fs;
+
+use crate::frontmatter::split_frontmatter;
+use crate::vault::resolve_in_vault;
+
+// Underline characters for heading levels 1 through 6
+const ADORNMENTS: [char; 6] = ['=', '-', '~', '^', '"', '\''];
+
+// Block-level nesting: the indent for the block's lines, and the list marker still
+// waiting to prefix the first line of a list item
+struct Block {
+    indent: String,
+    marker: Option<String>,
+}
+
+#[derive(Default)]
+struct RstWriter {
+    output: String,
+    // Inline text of the block being read, written out when the block ends
+    inline: String,
+    blocks: Vec<Block>,
+    // Next number for each open list; None for bullet lists
+    lists: Vec<Option<u64>>,
+    // Where the text of each open link starts in `inline`
+    links: Vec<usize>,
+    in_code: bool,
+}
+
+fn escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        if matches!(c, '\\' | '*' | '`' | '_' | '|') {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+    escaped
+}
+
+impl RstWriter {
+    fn indent(&self) -> String {
+        self.blocks
+            .last()
+            .map(|b| b.indent.clone())
+            .unwrap_or_default()
+    }
+
+    // Write `lines` at the current indent, the first one behind a pending list marker
+    fn write_lines(&mut self, text: &str) {
+        let indent = self.indent();
+        let marker = self.blocks.last_mut().and_then(|b| b.marker.take());
+        for (i, line) in text.lines().enumerate() {
+            match (&marker, i) {
+                (Some(marker), 0) => self.output.push_str(marker),
+                _ if line.is_empty() => {}
+                _ => self.output.push_str(&indent),
+            }
+            self.output.push_str(line);
+            self.output.push('\n');
+        }
+        self.output.push('\n');
+    }
+
+    fn flush(&mut self) {
+        let text = std::mem::take(&mut self.inline);
+        let text = text.trim();
+        if !text.is_empty() {
+            self.write_lines(text);
+        }
+    }
+
+    fn push_block(&mut self, extra: &str, marker: Option<String>) {
+        let indent = format!("{}{}", self.indent(), extra);
+        self.blocks.push(Block { indent, marker });
+    }
+
+    fn start(&mut self, tag: Tag) {
+        match tag {
+            Tag::Paragraph | Tag::Heading { .. } | Tag::TableRow | Tag::TableHead => self.flush(),
+            Tag::BlockQuote(_) => {
+                self.flush();
+                self.push_block("   ", None);
+            }
+            Tag::CodeBlock(kind) => {
+                self.flush();
+                let directive = match kind {
+                    CodeBlockKind::Fenced(info) => match info.split_whitespace().next() {
+                        Some(lang) => format!(".. code-block:: {}", lang),
+                        None => "::".to_string(),
+                    },
+                    CodeBlockKind::Indented => "::".to_string(),
+                };
+                self.write_lines(&directive);
+                self.push_block("   ", None);
+                self.in_code = true;
+            }
+            Tag::List(start) => {
+                self.flush();
+                self.lists.push(start);
+            }
+            Tag::Item => {
+                self.flush();
+                let marker = match self.lists.last_mut() {
+                    Some(Some(number)) => {
+                        *number += 1;
+                        format!("{}. ", *number - 1)
+                    }
+                    _ => "- ".to_string(),
+                };
+                let marker = format!("{}{}", self.indent(), marker);
+                let width = " ".repeat(marker.len() - self.indent().len());
+                self.push_block(&width, Some(marker));
+            }
+            Tag::Emphasis => self.inline.push('*'),
+            Tag::Strong => self.inline.push_str("**"),
+            Tag::Link { .. } | Tag::Image { .. } => self.links.push(self.inline.len()),
+            _ => {}
+        }
+    }
+
+    fn end(&mut self, tag: TagEnd) {
+        match tag {
+            TagEnd::Paragraph | TagEnd::TableHead | TagEnd::TableRow => self.flush(),
+            TagEnd::Heading(level) => {
+                let text = std::mem::take(&mut self.inline);
+                let text = text.trim();
+                let underline = ADORNMENTS[level as usize - 1]
+                    .to_string()
+                    .repeat(text.chars().count().max(1));
+                self.write_lines(&format!("{}\n{}", text, underline));
+            }
+            TagEnd::BlockQuote(_) | TagEnd::Item => {
+                self.flush();
+                self.blocks.pop();
+            }
+            TagEnd::CodeBlock => {
+                let code = std::mem::take(&mut self.inline);
+                if !code.trim().is_empty() {
+                    self.write_lines(code.trim_end_matches('\n'));
+                }
+                self.blocks.pop();
+                self.in_code = false;
+            }
+            TagEnd::List(_) => {
+                self.flush();
+                self.lists.pop();
+            }
+            TagEnd::TableCell => self.inline.push_str("  "),
+            TagEnd::Emphasis => self.inline.push('*'),
+            TagEnd::Strong => self.inline.push_str("**"),
+            TagEnd::Link | TagEnd::Image => {
+                // Filled in by `link`, which has the destination
+            }
+            _ => {}
+        }
+    }
+
+    // Turn the text collected since the link opened into an anonymous RST reference
+    fn link(&mut self, dest: &str) {
+        let Some(start) = self.links.pop() else {
+            return;
+        };
+        let text = self.inline.split_off(start);
+        let text = if text.trim().is_empty() {
+            escape(dest)
+        } else {
+            text
+        };
+        self.inline
+            .push_str(&format!("`{} <{}>`__", text.trim(), dest));
+    }
+}
+
+pub(crate) fn markdown_to_rst(content: &str) -> String {
+    let (_, body) = split_frontmatter(content);
+    let mut writer = RstWriter::default();
+    let mut destinations: Vec<String> = Vec::new();
+
+    for event in Parser::new_ext(body, Options::all()) {
+        match event {
+            Event::Start(tag) => {
+                if let Tag::Link { dest_url, .. } | Tag::Image { dest_url, .. } = &tag {
+                    destinations.push(dest_url.to_string());
+                }
+                writer.start(tag);
+            }
+            Event::End(tag) => {
+                let is_link = matches!(tag, TagEnd::Link | TagEnd::Image);
+                writer.end(tag);
+                if is_link {
+                    let dest = destinations.pop().unwrap_or_default();
+                    writer.link(&dest);
+                }
+            }
+            Event::Text(text) if writer.in_code => writer.inline.push_str(&text),
+            Event::Text(text) => writer.inline.push_str(&escape(&text)),
+            Event::Code(code) => writer.inline.push_str(&format!("``{}``", code)),
+            Event::SoftBreak | Event::HardBreak => writer.inline.push('\n'),
+            Event::Rule => {
+                writer.flush();
+                writer.write_lines("----");
+            }
+            Event::TaskListMarker(done) => {
+                writer.inline.push_str(if done { "[x] " } else { "[ ] " })
+            }
+            // Raw HTML, math and the like have no RST equivalent, so keep their text
+            Event::Html(text) | Event::InlineHtml(text) => writer.inline.push_str(&escape(&text)),
+            Event::InlineMath(text) | Event::DisplayMath(text) => {
+                writer.inline.push_str(&escape(&text))
+            }
+            Event::FootnoteReference(label) => {
+                writer.inline.push_str(&format!("[{}]", escape(&label)))
+            }
+        }
+    }
+    writer.flush();
+
+    let mut rst = writer.output.trim_end().to_string();
+    rst.push('\n');
+    rst
+}
+
+// A note converted to reStructuredText, for Sphinx. Anything without an RST
+// counterpart comes through as plain text
+#[tauri::command]
+pub async fn export_to_rst(path: String) -> Result<String, String> {
+    let file_path = resolve_in_vault(&path)?;
+    let content =
+        fs::read_to_string(&file_path).map_err(|e| format!("Failed to read file: {}", e))?;
+    Ok(markdown_to_rst(&content))
+}
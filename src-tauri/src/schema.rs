@@ -0,0 +1,116 @@
+use serde::Serialize;
+use serde_json::{Map, Value};
+use std::fs;
+
+use crate::frontmatter::parse_frontmatter;
+use crate::vault::resolve_in_vault;
+
+#[derive(Debug, Serialize)]
+pub struct SchemaError {
+    path: String,
+    message: String,
+}
+
+fn type_name(value: &Value) -> &'static str {
+    match value {
+        Value::Null => "null",
+        Value::Bool(_) => "boolean",
+        Value::Number(_) => "number",
+        Value::String(_) => "string",
+        Value::Array(_) => "array",
+        Value::Object(_) => "object",
+    }
+}
+
+fn field_path(parent: &str, key: &str) -> String {
+    if parent.is_empty() {
+        key.to_string()
+    } else {
+        format!("{}.{}", parent, key)
+    }
+}
+
+// Supports the small subset of JSON schema that's useful for frontmatter:
+// type, enum, required, properties (for objects) and items (for arrays)
+fn check(value: &Value, schema: &Value, path: &str, errors: &mut Vec<SchemaError>) {
+    let Some(schema) = schema.as_object() else {
+        return;
+    };
+
+    if let Some(expected) = schema.get("type").and_then(|t| t.as_str()) {
+        let actual = type_name(value);
+        let matches = actual == expected || (expected == "integer" && value.is_i64());
+        if !matches {
+            errors.push(SchemaError {
+                path: path.to_string(),
+                message: format!("Expected {}, found {}", expected, actual),
+            });
+            return;
+        }
+    }
+
+    if let Some(allowed) = schema.get("enum").and_then(|e| e.as_array()) {
+        if !allowed.contains(value) {
+            let options: Vec<String> = allowed.iter().map(|v| v.to_string()).collect();
+            errors.push(SchemaError {
+                path: path.to_string(),
+                message: format!("Must be one of: {}", options.join(", ")),
+            });
+        }
+    }
+
+    if let Value::Object(fields) = value {
+        check_object(fields, schema, path, errors);
+    }
+
+    if let (Value::Array(items), Some(item_schema)) = (value, schema.get("items")) {
+        for (i, item) in items.iter().enumerate() {
+            check(item, item_schema, &format!("{}[{}]", path, i), errors);
+        }
+    }
+}
+
+fn check_object(
+    fields: &Map<String, Value>,
+    schema: &Map<String, Value>,
+    path: &str,
+    errors: &mut Vec<SchemaError>,
+) {
+    if let Some(required) = schema.get("required").and_then(|r| r.as_array()) {
+        for key in required.iter().filter_map(|k| k.as_str()) {
+            if !fields.contains_key(key) {
+                errors.push(SchemaError {
+                    path: field_path(path, key),
+                    message: "Required field is missing".to_string(),
+                });
+            }
+        }
+    }
+
+    if let Some(properties) = schema.get("properties").and_then(|p| p.as_object()) {
+        for (key, property_schema) in properties {
+            if let Some(field) = fields.get(key) {
+                check(field, property_schema, &field_path(path, key), errors);
+            }
+        }
+    }
+}
+
+// Check a note's frontmatter against a schema; an empty list means it's valid
+#[tauri::command]
+pub async fn validate_frontmatter(path: String, schema: Value) -> Result<Vec<SchemaError>, String> {
+    if !schema.is_object() {
+        return Err("Schema must be an object".to_string());
+    }
+
+    let file_path = resolve_in_vault(&path)?;
+    let content =
+        fs::read_to_string(&file_path).map_err(|e| format!("Failed to read file: {}", e))?;
+
+    // A note without frontmatter is checked as an empty mapping
+    let frontmatter = parse_frontmatter(&content).unwrap_or_else(|| Value::Object(Map::new()));
+
+    let mut errors = Vec::new();
+    check(&frontmatter, &schema, "", &mut errors);
+    Ok(errors)
+}
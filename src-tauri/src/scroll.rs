@@ -0,0 +1,54 @@
+use crate::config::{read_config, update_config};
+use crate::get_margherita_dir;
+use crate::vault::{relative_path, resolve_in_vault};
+
+fn clamp_fraction(fraction: f32) -> f32 {
+    if fraction.is_nan() {
+        0.0
+    } else {
+        fraction.clamp(0.0, 1.0)
+    }
+}
+
+// Remember where the user was in a note, so it can reopen at the same spot
+#[tauri::command]
+pub async fn save_scroll_position(path: String, fraction: f32) -> Result<(), String> {
+    let dir = get_margherita_dir()?;
+    let file_path = resolve_in_vault(&path)?;
+    if !file_path.is_file() {
+        return Err(format!("File not found: {}", path));
+    }
+    let name = relative_path(&dir, &file_path);
+
+    update_config(|config| {
+        // Drop positions for notes that were deleted or renamed since
+        config
+            .scroll_positions
+            .retain(|note, _| dir.join(note).is_file());
+        config
+            .scroll_positions
+            .insert(name, clamp_fraction(fraction));
+    })
+}
+
+// The saved scroll fraction for a note, or 0.0 (the top) if there isn't one
+#[tauri::command]
+pub async fn get_scroll_position(path: String) -> Result<f32, String> {
+    let dir = get_margherita_dir()?;
+    let file_path = resolve_in_vault(&path)?;
+    let name = relative_path(&dir, &file_path);
+
+    if !file_path.is_file() {
+        let known = read_config()?.scroll_positions.contains_key(&name);
+        if known {
+            update_config(|config| config.scroll_positions.remove(&name))?;
+        }
+        return Ok(0.0);
+    }
+
+    Ok(read_config()?
+        .scroll_positions
+        .get(&name)
+        .copied()
+        .unwrap_or(0.0))
+}
@@ -0,0 +1,146 @@
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::Path;
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use tauri::{AppHandle, Emitter, State};
+
+use crate::get_margherita_dir;
+use crate::title::note_title;
+use crate::vault::{active_notes, relative_path};
+
+// One note can't flood the results with every line it has
+const MAX_HITS_PER_NOTE: usize = 20;
+
+#[derive(Debug, Clone, Copy, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SearchMode {
+    // Case-insensitive match anywhere in the note
+    Text,
+    // Case-insensitive match against the note's title only
+    Title,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct SearchHit {
+    path: String,
+    line: usize,
+    snippet: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+struct StreamedHit {
+    search_id: u64,
+    hit: SearchHit,
+}
+
+#[derive(Debug, Clone, Serialize)]
+struct SearchComplete {
+    search_id: u64,
+    hits: usize,
+    cancelled: bool,
+}
+
+// The id of the newest streaming search; older ones stop when they see it change
+#[derive(Default)]
+pub struct SearchState {
+    latest: AtomicU64,
+}
+
+fn search_note(dir: &Path, path: &Path, query: &str, mode: SearchMode) -> Vec<SearchHit> {
+    let Ok(content) = fs::read_to_string(path) else {
+        return Vec::new();
+    };
+    let note = relative_path(dir, path);
+
+    match mode {
+        SearchMode::Title => note_title(&content)
+            .filter(|title| title.to_lowercase().contains(query))
+            .map(|title| SearchHit {
+                path: note,
+                line: 1,
+                snippet: title,
+            })
+            .into_iter()
+            .collect(),
+        SearchMode::Text => content
+            .lines()
+            .enumerate()
+            .filter(|(_, line)| line.to_lowercase().contains(query))
+            .take(MAX_HITS_PER_NOTE)
+            .map(|(i, line)| SearchHit {
+                path: note.clone(),
+                line: i + 1,
+                snippet: line.trim().to_string(),
+            })
+            .collect(),
+    }
+}
+
+fn normalize_query(query: &str) -> Result<String, String> {
+    let query = query.trim().to_lowercase();
+    if query.is_empty() {
+        return Err("Search query is empty".to_string());
+    }
+    Ok(query)
+}
+
+// Every hit in the vault at once
+#[tauri::command]
+pub async fn search_files(query: String, mode: SearchMode) -> Result<Vec<SearchHit>, String> {
+    let query = normalize_query(&query)?;
+    let dir = get_margherita_dir()?;
+    let mut hits = Vec::new();
+    for path in active_notes(&dir)? {
+        hits.extend(search_note(&dir, &path, &query, mode));
+    }
+    println!("Found {} hits for {:?}", hits.len(), query);
+    Ok(hits)
+}
+
+// Search the vault, emitting a `search-result` event per hit and `search-complete` at the
+// end. Starting another search (or cancel_search) stops this one at the next note
+#[tauri::command]
+pub async fn search_files_streaming(
+    query: String,
+    mode: SearchMode,
+    search_id: u64,
+    app: AppHandle,
+    state: State<'_, SearchState>,
+) -> Result<(), String> {
+    let query = normalize_query(&query)?;
+    let dir = get_margherita_dir()?;
+    state.latest.store(search_id, Ordering::SeqCst);
+
+    let mut hits = 0;
+    let mut cancelled = false;
+    for path in active_notes(&dir)? {
+        if state.latest.load(Ordering::SeqCst) != search_id {
+            cancelled = true;
+            break;
+        }
+        for hit in search_note(&dir, &path, &query, mode) {
+            app.emit("search-result", StreamedHit { search_id, hit })
+                .map_err(|e| format!("Failed to send search result: {}", e))?;
+            hits += 1;
+        }
+    }
+
+    app.emit(
+        "search-complete",
+        SearchComplete {
+            search_id,
+            hits,
+            cancelled,
+        },
+    )
+    .map_err(|e| format!("Failed to send search result: {}", e))?;
+    Ok(())
+}
+
+// Stop whatever streaming search is in flight
+#[tauri::command]
+pub async fn cancel_search(state: State<'_, SearchState>) -> Result<(), String> {
+    state.latest.fetch_add(1, Ordering::SeqCst);
+    Ok(())
+}
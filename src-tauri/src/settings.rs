@@ -0,0 +1,205 @@
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::sync::Mutex;
+
+use tauri::State;
+
+use crate::frontmatter::set_frontmatter_field;
+use crate::get_margherita_dir;
+use crate::ids::with_note_id;
+use crate::markdown::{parse_pipeline, ParseOptions};
+use crate::trash::DEFAULT_RETENTION_DAYS;
+
+// Per-vault preferences live next to the notes themselves
+const SETTINGS_FILE: &str = ".margherita.json";
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LineEnding {
+    #[default]
+    Lf,
+    Crlf,
+}
+
+impl LineEnding {
+    // Rewrite every CRLF, lone CR, or LF in `content` to this style
+    pub(crate) fn apply(self, content: &str) -> String {
+        let unified = content.replace("\r\n", "\n").replace('\r', "\n");
+        match self {
+            LineEnding::Lf => unified,
+            LineEnding::Crlf => unified.replace('\n', "\r\n"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct VaultSettings {
+    pub(crate) default_extension: String,
+    pub(crate) journal_folder: String,
+    pub(crate) templates_folder: String,
+    pub(crate) parse_options: ParseOptions,
+    // Off by default so notes keep whatever endings the user's platform writes
+    pub(crate) normalize_line_endings: bool,
+    pub(crate) line_ending: LineEnding,
+    // Days to keep trashed notes before purging them on startup; 0 keeps them forever
+    pub(crate) trash_retention_days: u64,
+    // Stamp a `modified:` field in the frontmatter whenever a note is saved
+    pub(crate) track_modified: bool,
+    // Give notes a stable frontmatter `id` the first time they're saved
+    pub(crate) assign_note_ids: bool,
+    // Move files dropped into inbox/ into the vault as they arrive
+    pub(crate) watch_inbox: bool,
+    // Refuse to save notes bigger than this many bytes; 0 means no limit
+    pub(crate) max_note_bytes: u64,
+}
+
+impl Default for VaultSettings {
+    fn default() -> Self {
+        VaultSettings {
+            default_extension: "md".to_string(),
+            journal_folder: "journal".to_string(),
+            templates_folder: "templates".to_string(),
+            parse_options: ParseOptions::default(),
+            normalize_line_endings: false,
+            line_ending: LineEnding::default(),
+            trash_retention_days: DEFAULT_RETENTION_DAYS,
+            track_modified: false,
+            assign_note_ids: false,
+            watch_inbox: false,
+            max_note_bytes: 0,
+        }
+    }
+}
+
+impl VaultSettings {
+    // What a note's content becomes when it's saved: id, modified stamp, line endings
+    pub(crate) fn prepare_for_save(&self, content: String) -> String {
+        let content = if self.assign_note_ids {
+            with_note_id(content)
+        } else {
+            content
+        };
+        let content = if self.track_modified {
+            let now = chrono::Utc::now().to_rfc3339_opts(chrono::SecondsFormat::Secs, true);
+            set_frontmatter_field(&content, "modified", &now)
+        } else {
+            content
+        };
+        if self.normalize_line_endings {
+            self.line_ending.apply(&content)
+        } else {
+            content
+        }
+    }
+}
+
+// Settings loaded on startup, kept in memory so commands don't hit the disk
+pub struct SettingsState(pub(crate) Mutex<VaultSettings>);
+
+impl SettingsState {
+    pub fn load() -> Self {
+        SettingsState(Mutex::new(load_settings()))
+    }
+
+    pub(crate) fn get(&self) -> Result<VaultSettings, String> {
+        self.0.lock().map(|s| s.clone()).map_err(|e| e.to_string())
+    }
+}
+
+// Read .margherita.json, falling back to defaults when it's missing or malformed
+fn load_settings() -> VaultSettings {
+    let path = match get_margherita_dir() {
+        Ok(dir) => dir.join(SETTINGS_FILE),
+        Err(_) => return VaultSettings::default(),
+    };
+
+    let content = match fs::read_to_string(&path) {
+        Ok(content) => content,
+        Err(_) => {
+            println!("No vault settings found, using defaults");
+            return VaultSettings::default();
+        }
+    };
+
+    match serde_json::from_str(&content) {
+        Ok(settings) => {
+            println!("Loaded vault settings from {:?}", path);
+            settings
+        }
+        Err(e) => {
+            println!("Malformed vault settings, using defaults: {}", e);
+            VaultSettings::default()
+        }
+    }
+}
+
+#[tauri::command]
+pub async fn get_vault_settings(state: State<'_, SettingsState>) -> Result<VaultSettings, String> {
+    state.get()
+}
+
+fn save_settings(settings: VaultSettings, state: &SettingsState) -> Result<(), String> {
+    let dir = get_margherita_dir()?;
+    let json = serde_json::to_string_pretty(&settings)
+        .map_err(|e| format!("Failed to serialize settings: {}", e))?;
+    fs::write(dir.join(SETTINGS_FILE), json)
+        .map_err(|e| format!("Failed to save vault settings: {}", e))?;
+
+    *state.0.lock().map_err(|e| e.to_string())? = settings;
+    println!("Saved vault settings");
+    Ok(())
+}
+
+#[tauri::command]
+pub async fn update_vault_settings(
+    settings: VaultSettings,
+    state: State<'_, SettingsState>,
+) -> Result<(), String> {
+    let extension = settings.default_extension.trim_start_matches('.');
+    if extension != "md" && extension != "markdown" {
+        return Err(format!(
+            "Unsupported default extension: {}",
+            settings.default_extension
+        ));
+    }
+
+    let settings = VaultSettings {
+        default_extension: extension.to_string(),
+        ..settings
+    };
+    save_settings(settings, &state)
+}
+
+// Choose which post-processing stages run when rendering, and in what order
+#[tauri::command]
+pub async fn set_pipeline(
+    stages: Vec<String>,
+    state: State<'_, SettingsState>,
+) -> Result<(), String> {
+    let pipeline = parse_pipeline(&stages)?;
+    let mut settings = state.get()?;
+    settings.parse_options.set_pipeline(pipeline);
+    save_settings(settings, &state)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn crlf_is_saved_as_lf() {
+        assert_eq!(LineEnding::Lf.apply("a\r\nb\r\n"), "a\nb\n");
+    }
+
+    #[test]
+    fn lf_is_saved_as_crlf() {
+        assert_eq!(LineEnding::Crlf.apply("a\nb\n"), "a\r\nb\r\n");
+    }
+
+    #[test]
+    fn mixed_endings_are_unified() {
+        assert_eq!(LineEnding::Crlf.apply("a\r\nb\rc\n"), "a\r\nb\r\nc\r\n");
+        assert_eq!(LineEnding::Lf.apply("a\r\nb\rc\n"), "a\nb\nc\n");
+    }
+}
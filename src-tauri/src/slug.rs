@@ -0,0 +1,44 @@
+use deunicode::deunicode;
+use std::fs;
+use std::path::Path;
+
+use crate::frontmatter::{parse_frontmatter, string_field};
+use crate::title::note_title;
+use crate::vault::resolve_in_vault;
+
+// Lowercase, ASCII-only, hyphen-separated form of any text
+pub(crate) fn slugify(text: &str) -> String {
+    let mut slug = String::with_capacity(text.len());
+    for c in deunicode(text).chars() {
+        if c.is_ascii_alphanumeric() {
+            slug.push(c.to_ascii_lowercase());
+        } else if !slug.is_empty() && !slug.ends_with('-') {
+            slug.push('-');
+        }
+    }
+    slug.trim_end_matches('-').to_string()
+}
+
+// Frontmatter slug first, then the note's title, then the file name
+#[tauri::command]
+pub async fn slug_for_note(path: String) -> Result<String, String> {
+    let file_path = resolve_in_vault(&path)?;
+    let content =
+        fs::read_to_string(&file_path).map_err(|e| format!("Failed to read file: {}", e))?;
+
+    let frontmatter = parse_frontmatter(&content);
+    let candidates = [
+        frontmatter.as_ref().and_then(|f| string_field(f, "slug")),
+        note_title(&content),
+        Path::new(&path)
+            .file_stem()
+            .map(|s| s.to_string_lossy().into_owned()),
+    ];
+
+    candidates
+        .into_iter()
+        .flatten()
+        .map(|candidate| slugify(&candidate))
+        .find(|slug| !slug.is_empty())
+        .ok_or_else(|| format!("Could not derive a slug for {}", path))
+}
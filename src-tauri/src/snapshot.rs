@@ -0,0 +1,97 @@
+use serde::Serialize;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::get_margherita_dir;
+use crate::lock::ensure_unlocked;
+use crate::util::now_millis;
+use crate::vault::{relative_path, resolve_in_vault, write_atomic};
+
+// Earlier versions of notes, as .snapshots/<note path>/<millis>.md
+pub(crate) const SNAPSHOT_DIR: &str = ".snapshots";
+
+#[derive(Debug, Serialize)]
+pub struct RestoredSnapshot {
+    content: String,
+    // The snapshot taken of the note just before it was overwritten, if it existed
+    safety_snapshot: Option<u64>,
+}
+
+fn snapshot_folder(dir: &Path, note: &str) -> PathBuf {
+    dir.join(SNAPSHOT_DIR).join(note)
+}
+
+// Save a copy of the note as it is on disk now; returns the snapshot's timestamp
+pub(crate) fn take_snapshot(dir: &Path, note: &str) -> Result<u64, String> {
+    let content =
+        fs::read_to_string(dir.join(note)).map_err(|e| format!("Failed to read file: {}", e))?;
+    let folder = snapshot_folder(dir, note);
+    fs::create_dir_all(&folder).map_err(|e| format!("Failed to create snapshot folder: {}", e))?;
+
+    // Two snapshots in the same millisecond still get their own files
+    let mut timestamp = now_millis() as u64;
+    while folder.join(format!("{}.md", timestamp)).exists() {
+        timestamp += 1;
+    }
+    write_atomic(&folder.join(format!("{}.md", timestamp)), &content)?;
+    println!("Snapshotted {} at {}", note, timestamp);
+    Ok(timestamp)
+}
+
+#[tauri::command]
+pub async fn create_snapshot(name: String) -> Result<u64, String> {
+    let dir = get_margherita_dir()?;
+    let note = relative_path(&dir, &resolve_in_vault(&name)?);
+    take_snapshot(&dir, &note)
+}
+
+// Timestamps of a note's snapshots, newest first
+#[tauri::command]
+pub async fn list_snapshots(name: String) -> Result<Vec<u64>, String> {
+    let dir = get_margherita_dir()?;
+    let note = relative_path(&dir, &resolve_in_vault(&name)?);
+    let Ok(entries) = fs::read_dir(snapshot_folder(&dir, &note)) else {
+        return Ok(Vec::new());
+    };
+
+    let mut timestamps: Vec<u64> = entries
+        .flatten()
+        .filter_map(|entry| {
+            let name = entry.file_name();
+            name.to_str()?.strip_suffix(".md")?.parse().ok()
+        })
+        .collect();
+    timestamps.sort_unstable_by(|a, b| b.cmp(a));
+    Ok(timestamps)
+}
+
+// Put a snapshot back in place of the note. The current version is snapshotted first,
+// so a restore can itself be undone by restoring that one
+#[tauri::command]
+pub async fn restore_snapshot(name: String, timestamp: u64) -> Result<RestoredSnapshot, String> {
+    let dir = get_margherita_dir()?;
+    let file_path = resolve_in_vault(&name)?;
+    let note = relative_path(&dir, &file_path);
+    ensure_unlocked(&note)?;
+
+    let snapshot = snapshot_folder(&dir, &note).join(format!("{}.md", timestamp));
+    let content = fs::read_to_string(&snapshot)
+        .map_err(|_| format!("No snapshot of {} at {}", note, timestamp))?;
+
+    let safety_snapshot = if file_path.is_file() {
+        Some(take_snapshot(&dir, &note)?)
+    } else {
+        None
+    };
+
+    if let Some(parent) = file_path.parent() {
+        fs::create_dir_all(parent).map_err(|e| format!("Failed to create directory: {}", e))?;
+    }
+    write_atomic(&file_path, &content)?;
+    println!("Restored {} from snapshot {}", note, timestamp);
+
+    Ok(RestoredSnapshot {
+        content,
+        safety_snapshot,
+    })
+}
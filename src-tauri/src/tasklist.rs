@@ -0,0 +1,93 @@
+use pulldown_cmark::{Event, Options, Parser, Tag};
+use std::ops::Range;
+
+use crate::frontmatter::split_frontmatter;
+use crate::links::apply_edits;
+
+// Stand-ins for task checkboxes in places that can't render task lists
+const UNCHECKED_BOX: &str = "☐";
+const CHECKED_BOX: &str = "☑";
+const UNCHECKED_LITERAL: &str = "\\[ ]";
+const CHECKED_LITERAL: &str = "\\[x]";
+
+// Length of a list item's `- ` / `1. ` marker and the spaces after it
+fn list_marker_len(item: &str) -> Option<usize> {
+    let digits = item.bytes().take_while(u8::is_ascii_digit).count();
+    let marker = match item.as_bytes().get(digits)? {
+        b'.' | b')' if digits > 0 => digits + 1,
+        b'-' | b'*' | b'+' if digits == 0 => 1,
+        _ => return None,
+    };
+    let spaces = item[marker..]
+        .bytes()
+        .take_while(|b| *b == b' ' || *b == b'\t')
+        .count();
+    (spaces > 0).then_some(marker + spaces)
+}
+
+fn body_events(content: &str) -> (usize, Vec<(Event<'_>, Range<usize>)>) {
+    let (_, body) = split_frontmatter(content);
+    let offset = content.len() - body.len();
+    let events = Parser::new_ext(body, Options::all())
+        .into_offset_iter()
+        .collect();
+    (offset, events)
+}
+
+// Turn `- [ ]` / `- [x]` items into plain bullets starting with ☐ / ☑, or with escaped
+// brackets that render as literal `[ ]` / `[x]` when `literal` is set
+#[tauri::command]
+pub async fn tasks_to_bullets(content: String, literal: Option<bool>) -> Result<String, String> {
+    let (unchecked, checked) = if literal.unwrap_or(false) {
+        (UNCHECKED_LITERAL, CHECKED_LITERAL)
+    } else {
+        (UNCHECKED_BOX, CHECKED_BOX)
+    };
+
+    let (offset, events) = body_events(&content);
+    let edits = events
+        .into_iter()
+        .filter_map(|(event, range)| match event {
+            Event::TaskListMarker(done) => Some((
+                offset + range.start..offset + range.end,
+                if done { checked } else { unchecked }.to_string(),
+            )),
+            _ => None,
+        })
+        .collect();
+
+    Ok(apply_edits(&content, edits))
+}
+
+// The inverse of tasks_to_bullets: bullets starting with ☐ / ☑ (or escaped `[ ]` /
+// `[x]`) become task items again
+#[tauri::command]
+pub async fn bullets_to_tasks(content: String) -> Result<String, String> {
+    let (offset, events) = body_events(&content);
+    let mut edits = Vec::new();
+
+    for (event, range) in events {
+        if !matches!(event, Event::Start(Tag::Item)) {
+            continue;
+        }
+        let item = offset + range.start;
+        let Some(marker_len) = list_marker_len(&content[item..]) else {
+            continue;
+        };
+        let start = item + marker_len;
+        let text = &content[start..];
+        for (marker, task) in [
+            (UNCHECKED_BOX, "[ ]"),
+            (CHECKED_BOX, "[x]"),
+            (UNCHECKED_LITERAL, "[ ]"),
+            (CHECKED_LITERAL, "[x]"),
+        ] {
+            if text.starts_with(marker) {
+                edits.push((start..start + marker.len(), task.to_string()));
+                break;
+            }
+        }
+    }
+
+    Ok(apply_edits(&content, edits))
+}
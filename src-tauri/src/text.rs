@@ -0,0 +1,33 @@
+use pulldown_cmark::{Event, Options, Parser, Tag, TagEnd};
+
+use crate::frontmatter::split_frontmatter;
+
+// The readable text of a note: no frontmatter, markup, or code blocks
+pub(crate) fn plain_text(content: &str) -> String {
+    let (_, body) = split_frontmatter(content);
+    let mut text = String::with_capacity(body.len());
+    let mut in_code_block = false;
+
+    for event in Parser::new_ext(body, Options::all()) {
+        match event {
+            Event::Start(Tag::CodeBlock(_)) => in_code_block = true,
+            Event::End(TagEnd::CodeBlock) => in_code_block = false,
+            Event::Text(t) | Event::Code(t) if !in_code_block => text.push_str(&t),
+            Event::SoftBreak | Event::HardBreak => text.push(' '),
+            Event::End(TagEnd::Paragraph)
+            | Event::End(TagEnd::Heading(_))
+            | Event::End(TagEnd::Item)
+            | Event::End(TagEnd::TableCell) => text.push('\n'),
+            _ => {}
+        }
+    }
+    text
+}
+
+pub(crate) fn word_count(content: &str) -> usize {
+    plain_text(content).split_whitespace().count()
+}
+
+pub(crate) fn char_count(content: &str) -> usize {
+    plain_text(content).trim().chars().count()
+}
@@ -0,0 +1,63 @@
+use chrono::NaiveDate;
+use std::path::Path;
+
+use crate::frontmatter::read_frontmatter_block;
+use crate::get_margherita_dir;
+use crate::vault::{active_notes, relative_path};
+
+// Parse a YYYY-MM-DD date at the start of `text`, ignoring any time that follows
+fn date_prefix(text: &str) -> Option<NaiveDate> {
+    let prefix = text.trim().get(..10)?;
+    NaiveDate::parse_from_str(prefix, "%Y-%m-%d").ok()
+}
+
+// The first YYYY-MM-DD anywhere in a file name, e.g. "2024-03-01 standup.md"
+fn date_in_name(name: &str) -> Option<NaiveDate> {
+    name.char_indices()
+        .filter(|(_, c)| c.is_ascii_digit())
+        .find_map(|(i, _)| date_prefix(&name[i..]))
+}
+
+// A note's date: its frontmatter `date` field, else a date in its file name
+pub(crate) fn note_date(path: &Path) -> Option<NaiveDate> {
+    let from_frontmatter = read_frontmatter_block(path)
+        .ok()
+        .flatten()
+        .and_then(|yaml| serde_yaml::from_str::<serde_json::Value>(&yaml).ok())
+        .and_then(|frontmatter| {
+            frontmatter
+                .get("date")
+                .and_then(|d| d.as_str())
+                .and_then(date_prefix)
+        });
+
+    from_frontmatter.or_else(|| date_in_name(&path.file_stem()?.to_string_lossy()))
+}
+
+fn parse_bound(bound: &str) -> Result<NaiveDate, String> {
+    NaiveDate::parse_from_str(bound.trim(), "%Y-%m-%d")
+        .map_err(|e| format!("Invalid date {}: {}", bound, e))
+}
+
+// Notes dated between `start` and `end` (inclusive), oldest first
+#[tauri::command]
+pub async fn notes_in_range(start: String, end: String) -> Result<Vec<String>, String> {
+    let start = parse_bound(&start)?;
+    let end = parse_bound(&end)?;
+    if start > end {
+        return Err(format!("Start date {} is after end date {}", start, end));
+    }
+
+    let dir = get_margherita_dir()?;
+    let mut dated: Vec<(NaiveDate, String)> = active_notes(&dir)?
+        .iter()
+        .filter_map(|path| {
+            let date = note_date(path)?;
+            (start <= date && date <= end).then(|| (date, relative_path(&dir, path)))
+        })
+        .collect();
+
+    dated.sort();
+    println!("Found {} notes between {} and {}", dated.len(), start, end);
+    Ok(dated.into_iter().map(|(_, path)| path).collect())
+}
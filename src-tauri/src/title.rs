@@ -0,0 +1,95 @@
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::fs;
+
+use crate::frontmatter::{parse_frontmatter, string_field};
+use crate::get_margherita_dir;
+use crate::markdown::first_h1;
+use crate::text::plain_text;
+use crate::vault::{active_notes, relative_path};
+
+// Notes that share a title; `title` is spelled as in the first of them
+#[derive(Debug, Serialize)]
+pub struct TitleCollision {
+    title: String,
+    notes: Vec<String>,
+}
+
+// What a note is called: its frontmatter title, else its first H1, else its first
+// line of text. Everything that shows or derives from a title should go through this
+pub(crate) fn note_title(content: &str) -> Option<String> {
+    parse_frontmatter(content)
+        .and_then(|f| string_field(&f, "title"))
+        .or_else(|| first_h1(content))
+        .or_else(|| {
+            plain_text(content)
+                .lines()
+                .map(str::trim)
+                .find(|line| !line.is_empty())
+                .map(|line| line.to_string())
+        })
+}
+
+// A note's title, or an empty string if it has no text at all
+#[tauri::command]
+pub async fn get_title(content: String) -> Result<String, String> {
+    Ok(note_title(&content).unwrap_or_default())
+}
+
+// Groups of notes whose titles match (ignoring case), which makes a wikilink by title
+// ambiguous. Notes without any text have no title and are left out
+#[tauri::command]
+pub async fn title_collisions() -> Result<Vec<TitleCollision>, String> {
+    let dir = get_margherita_dir()?;
+    let mut by_title: BTreeMap<String, TitleCollision> = BTreeMap::new();
+
+    for path in active_notes(&dir)? {
+        let Ok(content) = fs::read_to_string(&path) else {
+            continue;
+        };
+        let Some(title) = note_title(&content) else {
+            continue;
+        };
+        by_title
+            .entry(title.trim().to_lowercase())
+            .or_insert_with(|| TitleCollision {
+                title: title.trim().to_string(),
+                notes: Vec::new(),
+            })
+            .notes
+            .push(relative_path(&dir, &path));
+    }
+
+    Ok(by_title
+        .into_values()
+        .filter(|c| c.notes.len() > 1)
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn frontmatter_title_comes_first() {
+        let content = "---\ntitle: From Frontmatter\n---\n# From Heading\n";
+        assert_eq!(note_title(content).as_deref(), Some("From Frontmatter"));
+    }
+
+    #[test]
+    fn first_h1_without_a_frontmatter_title() {
+        let content = "---\ntags: [a]\n---\nIntro line\n\n# From Heading\n";
+        assert_eq!(note_title(content).as_deref(), Some("From Heading"));
+    }
+
+    #[test]
+    fn first_line_without_a_heading() {
+        let content = "\n\n  Just some **text**\n\nMore text\n";
+        assert_eq!(note_title(content).as_deref(), Some("Just some text"));
+    }
+
+    #[test]
+    fn no_title_without_text() {
+        assert_eq!(note_title("---\ntitle: \"\"\n---\n\n"), None);
+    }
+}
@@ -0,0 +1,236 @@
+use serde::Serialize;
+use std::collections::HashMap;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use tauri::State;
+
+use crate::get_margherita_dir;
+use crate::lock::locked_notes;
+use crate::mode::ensure_editable;
+use crate::util::{new_token, now_millis};
+use crate::vault::{relative_path, resolve_in_vault, unique_path};
+
+// Deleted notes are moved here, grouped by the time they were deleted
+pub(crate) const TRASH_DIR: &str = ".trash";
+
+// Trashed notes older than this are purged on startup unless the vault says otherwise
+pub(crate) const DEFAULT_RETENTION_DAYS: u64 = 30;
+
+const MILLIS_PER_DAY: u128 = 24 * 60 * 60 * 1000;
+
+// How long a bulk delete token stays valid after prepare_bulk_delete
+const TOKEN_TTL: Duration = Duration::from_secs(5 * 60);
+
+#[derive(Debug, Serialize)]
+pub struct BulkDeletePreview {
+    token: String,
+    files: Vec<String>,
+    missing: Vec<String>,
+    // Locked notes in the selection; bulk_delete refuses while there are any
+    locked: Vec<String>,
+    total_bytes: u64,
+}
+
+struct PendingDelete {
+    paths: Vec<String>,
+    issued_at: Instant,
+}
+
+// Tokens handed out by prepare_bulk_delete, waiting to be confirmed
+#[derive(Default)]
+pub struct BulkDeleteState {
+    pending: Mutex<HashMap<String, PendingDelete>>,
+}
+
+// Sort and dedupe so the same selection always compares equal
+fn normalize_paths(paths: Vec<String>) -> Vec<String> {
+    let mut paths: Vec<String> = paths
+        .into_iter()
+        .map(|p| p.trim().to_string())
+        .filter(|p| !p.is_empty())
+        .collect();
+    paths.sort();
+    paths.dedup();
+    paths
+}
+
+// Move a file into .trash/<millis>/<relative path>, keeping its folder structure.
+// The io error is kept so callers can tell a permission problem from the rest
+pub(crate) fn trash_file(dir: &Path, file_path: &Path, batch: u128) -> io::Result<PathBuf> {
+    let relative = file_path.strip_prefix(dir).unwrap_or(file_path);
+    let target = unique_path(dir.join(TRASH_DIR).join(batch.to_string()).join(relative));
+
+    if let Some(parent) = target.parent() {
+        fs::create_dir_all(parent)?;
+    }
+
+    fs::rename(file_path, &target)?;
+    println!("Moved {:?} to trash at {:?}", file_path, target);
+
+    Ok(target)
+}
+
+pub(crate) fn move_to_trash(dir: &Path, file_path: &Path, batch: u128) -> Result<PathBuf, String> {
+    ensure_editable()?;
+    trash_file(dir, file_path, batch).map_err(|e| format!("Failed to move file to trash: {}", e))
+}
+
+// First step of a bulk delete: report what would be removed and issue a token
+#[tauri::command]
+pub async fn prepare_bulk_delete(
+    paths: Vec<String>,
+    state: State<'_, BulkDeleteState>,
+) -> Result<BulkDeletePreview, String> {
+    let paths = normalize_paths(paths);
+    if paths.is_empty() {
+        return Err("No files selected for deletion".to_string());
+    }
+
+    let dir = get_margherita_dir()?;
+    let mut files = Vec::new();
+    let mut missing = Vec::new();
+    let mut locked = Vec::new();
+    let mut total_bytes = 0;
+    let locked_set = locked_notes()?;
+
+    for path in &paths {
+        let file_path = resolve_in_vault(path)?;
+        match fs::metadata(&file_path) {
+            Ok(meta) if meta.is_file() => {
+                let key = relative_path(&dir, &file_path);
+                if locked_set.contains(&key) {
+                    locked.push(key);
+                    continue;
+                }
+                total_bytes += meta.len();
+                files.push(key);
+            }
+            _ => missing.push(path.clone()),
+        }
+    }
+
+    let token = new_token();
+    let mut pending = state.pending.lock().map_err(|e| e.to_string())?;
+    pending.retain(|_, p| p.issued_at.elapsed() < TOKEN_TTL);
+    pending.insert(
+        token.clone(),
+        PendingDelete {
+            paths,
+            issued_at: Instant::now(),
+        },
+    );
+
+    Ok(BulkDeletePreview {
+        token,
+        files,
+        missing,
+        locked,
+        total_bytes,
+    })
+}
+
+// Second step: move the previewed files to the trash, only with a matching token
+#[tauri::command]
+pub async fn bulk_delete(
+    paths: Vec<String>,
+    confirm: String,
+    state: State<'_, BulkDeleteState>,
+) -> Result<usize, String> {
+    let paths = normalize_paths(paths);
+
+    let issued = {
+        let mut pending = state.pending.lock().map_err(|e| e.to_string())?;
+        pending
+            .remove(&confirm)
+            .ok_or_else(|| "Invalid or already used confirmation token".to_string())?
+    };
+
+    if issued.issued_at.elapsed() >= TOKEN_TTL {
+        return Err("Confirmation token has expired".to_string());
+    }
+    if issued.paths != paths {
+        return Err("Selection changed since the delete was prepared".to_string());
+    }
+
+    let dir = get_margherita_dir()?;
+    // Lock keys are vault-relative, so "./a.md" has to be checked as "a.md"
+    let locked = locked_notes()?;
+    for path in &paths {
+        let key = relative_path(&dir, &resolve_in_vault(path)?);
+        if locked.contains(&key) {
+            return Err(format!("Note is locked: {}", key));
+        }
+    }
+
+    let batch = now_millis();
+    let mut deleted = 0;
+
+    for path in &paths {
+        let file_path = resolve_in_vault(path)?;
+        if !file_path.is_file() {
+            println!("Skipping missing file: {:?}", file_path);
+            continue;
+        }
+        move_to_trash(&dir, &file_path, batch)?;
+        deleted += 1;
+    }
+
+    println!("Bulk deleted {} files", deleted);
+    Ok(deleted)
+}
+
+fn count_files(path: &Path) -> usize {
+    match fs::read_dir(path) {
+        Ok(entries) => entries
+            .flatten()
+            .map(|entry| {
+                let path = entry.path();
+                if path.is_dir() {
+                    count_files(&path)
+                } else {
+                    1
+                }
+            })
+            .sum(),
+        Err(_) => 0,
+    }
+}
+
+// Remove trash batches deleted more than `days` ago, returning how many files went with them.
+// Batches are named after the time they were trashed, so that's what decides their age
+pub(crate) fn purge_older_than(days: u64) -> Result<usize, String> {
+    let trash_dir = get_margherita_dir()?.join(TRASH_DIR);
+    if !trash_dir.exists() {
+        return Ok(0);
+    }
+
+    let cutoff = now_millis().saturating_sub(days as u128 * MILLIS_PER_DAY);
+    let entries =
+        fs::read_dir(&trash_dir).map_err(|e| format!("Failed to read trash folder: {}", e))?;
+    let mut purged = 0;
+
+    for entry in entries.flatten() {
+        let Ok(batch) = entry.file_name().to_string_lossy().parse::<u128>() else {
+            continue;
+        };
+        if batch > cutoff {
+            continue;
+        }
+        let path = entry.path();
+        let files = count_files(&path);
+        fs::remove_dir_all(&path).map_err(|e| format!("Failed to purge trash: {}", e))?;
+        purged += files;
+    }
+
+    println!("Purged {} files from trash", purged);
+    Ok(purged)
+}
+
+#[tauri::command]
+pub async fn purge_trash(older_than_days: u64) -> Result<usize, String> {
+    purge_older_than(older_than_days)
+}
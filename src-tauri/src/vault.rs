@@ -0,0 +1,244 @@
+use std::collections::HashSet;
+use std::fs;
+use std::io::{self, Read};
+use std::path::{Component, Path, PathBuf};
+
+use crate::archive::ARCHIVE_DIR;
+use crate::get_margherita_dir;
+use crate::mode::ensure_editable;
+
+// Resolve a path relative to the margherita directory, rejecting anything
+// that could point outside of it (absolute paths, `..`, drive prefixes)
+pub(crate) fn resolve_in_vault(path: &str) -> Result<PathBuf, String> {
+    if path.trim().is_empty() {
+        return Err("Path is empty".to_string());
+    }
+
+    for component in Path::new(path).components() {
+        match component {
+            Component::Normal(_) | Component::CurDir => {}
+            _ => {
+                return Err(format!(
+                    "Path is outside the margherita directory: {}",
+                    path
+                ))
+            }
+        }
+    }
+
+    Ok(get_margherita_dir()?.join(path))
+}
+
+// Turn an absolute path inside the vault back into the relative form the UI uses
+pub(crate) fn relative_path(dir: &Path, path: &Path) -> String {
+    path.strip_prefix(dir)
+        .unwrap_or(path)
+        .components()
+        .map(|c| c.as_os_str().to_string_lossy())
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
+// Pick a path that doesn't exist yet by appending " (n)" to the file stem
+pub(crate) fn unique_path(path: PathBuf) -> PathBuf {
+    if !path.exists() {
+        return path;
+    }
+
+    let stem = path
+        .file_stem()
+        .map(|s| s.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    let ext = path
+        .extension()
+        .map(|e| format!(".{}", e.to_string_lossy()))
+        .unwrap_or_default();
+
+    let mut counter = 1;
+    loop {
+        let candidate = path.with_file_name(format!("{} ({}){}", stem, counter, ext));
+        if !candidate.exists() {
+            return candidate;
+        }
+        counter += 1;
+    }
+}
+
+// Only markdown files count as notes
+pub(crate) fn is_note(path: &Path) -> bool {
+    matches!(
+        path.extension().and_then(|e| e.to_str()),
+        Some("md") | Some("markdown")
+    )
+}
+
+// Images and documents that notes embed or link to
+const ATTACHMENT_EXTENSIONS: &[&str] = &[
+    "png", "jpg", "jpeg", "gif", "svg", "webp", "bmp", "pdf", "mp3", "mp4", "wav", "webm",
+];
+
+pub(crate) fn is_attachment(path: &Path) -> bool {
+    path.extension()
+        .and_then(|e| e.to_str())
+        .map(|e| ATTACHMENT_EXTENSIONS.contains(&e.to_ascii_lowercase().as_str()))
+        .unwrap_or(false)
+}
+
+// A note found while walking the vault, with the metadata of what it points to
+pub(crate) struct NoteEntry {
+    pub(crate) path: PathBuf,
+    pub(crate) meta: fs::Metadata,
+    pub(crate) is_symlink: bool,
+}
+
+// Walk every note under `root`, skipping hidden folders like .trash. Symlinks are
+// followed, but each real folder is only entered once so a link loop can't recurse forever
+pub(crate) fn walk_notes(root: &Path) -> Result<Vec<NoteEntry>, String> {
+    let mut notes = Vec::new();
+    let mut visited = HashSet::new();
+    let mut pending = vec![root.to_path_buf()];
+
+    while let Some(dir) = pending.pop() {
+        let real = fs::canonicalize(&dir).unwrap_or_else(|_| dir.clone());
+        if !visited.insert(real) {
+            println!("Skipping already visited folder {:?}", dir);
+            continue;
+        }
+
+        let entries = fs::read_dir(&dir).map_err(|e| format!("Failed to read directory: {}", e))?;
+        for entry in entries.flatten() {
+            let path = entry.path();
+            let hidden = entry.file_name().to_string_lossy().starts_with('.');
+            let is_symlink = entry.file_type().is_ok_and(|t| t.is_symlink());
+            // fs::metadata follows the link; a dangling one has nothing to list
+            let Ok(meta) = fs::metadata(&path) else {
+                continue;
+            };
+            if meta.is_dir() && !hidden {
+                pending.push(path);
+            } else if meta.is_file() && is_note(&path) {
+                notes.push(NoteEntry {
+                    path,
+                    meta,
+                    is_symlink,
+                });
+            }
+        }
+    }
+
+    notes.sort_by(|a, b| a.path.cmp(&b.path));
+    Ok(notes)
+}
+
+// Recursively collect every note under `root`, skipping hidden folders like .trash
+pub(crate) fn collect_notes(root: &Path) -> Result<Vec<PathBuf>, String> {
+    Ok(walk_notes(root)?.into_iter().map(|n| n.path).collect())
+}
+
+// Notes the user is actively working with: everything except the archive
+pub(crate) fn active_notes(dir: &Path) -> Result<Vec<PathBuf>, String> {
+    let archive_dir = dir.join(ARCHIVE_DIR);
+    Ok(collect_notes(dir)?
+        .into_iter()
+        .filter(|p| !p.starts_with(&archive_dir))
+        .collect())
+}
+
+// Refuse writes that would land outside the vault once symlinks are resolved, e.g.
+// through a linked note or folder that points elsewhere. Returns the real path
+pub(crate) fn ensure_inside_vault(path: &Path) -> Result<PathBuf, String> {
+    let vault = fs::canonicalize(get_margherita_dir()?)
+        .map_err(|e| format!("Failed to resolve the margherita directory: {}", e))?;
+
+    // Resolve the deepest part of the path that exists (a link counts, even a broken one)
+    let mut existing = path;
+    let mut rest = Vec::new();
+    while existing.symlink_metadata().is_err() {
+        let (Some(parent), Some(name)) = (existing.parent(), existing.file_name()) else {
+            break;
+        };
+        rest.push(name);
+        existing = parent;
+    }
+    let mut real =
+        fs::canonicalize(existing).map_err(|_| format!("Path is a broken link: {:?}", existing))?;
+    real.extend(rest.iter().rev());
+
+    if !real.starts_with(&vault) {
+        return Err(format!(
+            "Path is outside the margherita directory: {:?}",
+            path
+        ));
+    }
+    Ok(real)
+}
+
+// resolve_in_vault for paths about to be written, with symlinks taken into account
+pub(crate) fn resolve_for_write(path: &str) -> Result<PathBuf, String> {
+    let resolved = resolve_in_vault(path)?;
+    ensure_inside_vault(&resolved)?;
+    Ok(resolved)
+}
+
+// Write through a temp file and rename, so a crash never leaves a half-written note.
+// A symlinked note is written where it points, so the link itself survives
+pub(crate) fn write_atomic(path: &Path, content: &str) -> Result<(), String> {
+    write_atomic_from(path, &mut content.as_bytes())
+}
+
+// write_atomic for content streamed from elsewhere, like a zip entry; nothing is
+// replaced unless the whole of `reader` was copied
+pub(crate) fn write_atomic_from(path: &Path, reader: &mut impl Read) -> Result<(), String> {
+    ensure_editable()?;
+    let real = ensure_inside_vault(path)?;
+    let path = real.as_path();
+    let file_name = path
+        .file_name()
+        .map(|n| n.to_string_lossy().into_owned())
+        .ok_or_else(|| format!("Invalid file path: {:?}", path))?;
+    let temp_path = path.with_file_name(format!(".{}.tmp", file_name));
+
+    fs::File::create(&temp_path)
+        .and_then(|mut file| io::copy(reader, &mut file))
+        .map_err(|e| {
+            let _ = fs::remove_file(&temp_path);
+            format!("Failed to write file: {}", e)
+        })?;
+    fs::rename(&temp_path, path).map_err(|e| {
+        let _ = fs::remove_file(&temp_path);
+        format!("Failed to replace file: {}", e)
+    })
+}
+
+// Collapse `.` and `..` in a vault-relative path; None if it climbs out of the vault
+pub(crate) fn normalize_relative(path: &str) -> Option<String> {
+    let mut parts: Vec<&str> = Vec::new();
+    for part in path.split('/') {
+        match part {
+            "" | "." => {}
+            ".." => {
+                parts.pop()?;
+            }
+            _ => parts.push(part),
+        }
+    }
+    Some(parts.join("/"))
+}
+
+// Folder part of a vault-relative path ("" for notes in the root)
+pub(crate) fn parent_of(path: &str) -> &str {
+    path.rsplit_once('/')
+        .map(|(parent, _)| parent)
+        .unwrap_or("")
+}
+
+// Relative path from a folder to a vault-relative target, e.g. "../work/note.md"
+pub(crate) fn relative_from(from_dir: &str, to: &str) -> String {
+    let from: Vec<&str> = from_dir.split('/').filter(|p| !p.is_empty()).collect();
+    let to: Vec<&str> = to.split('/').filter(|p| !p.is_empty()).collect();
+
+    let common = from.iter().zip(&to).take_while(|(a, b)| a == b).count();
+    let mut parts = vec![".."; from.len() - common];
+    parts.extend(&to[common..]);
+    parts.join("/")
+}
@@ -0,0 +1,150 @@
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::get_margherita_dir;
+use crate::links::{file_stem, strip_note_extension};
+use crate::title::note_title;
+use crate::vault::{active_notes, relative_path};
+
+// Why a wikilink target didn't resolve to a single note
+pub(crate) enum Unresolved {
+    Missing,
+    Ambiguous(Vec<String>),
+}
+
+impl Unresolved {
+    pub(crate) fn describe(&self, target: &str) -> String {
+        match self {
+            Unresolved::Missing => format!("No note matches [[{}]]", target),
+            Unresolved::Ambiguous(candidates) => format!(
+                "[[{}]] could mean any of: {}",
+                target,
+                candidates.join(", ")
+            ),
+        }
+    }
+}
+
+// Matches `[[Name]]` targets to notes, the same way everywhere: a note with that file
+// name, else the one note with that title, else `[[folder/Name]]` as a path from the
+// vault root. Lookups ignore case and note extensions
+pub(crate) struct WikiResolver {
+    notes: Vec<String>,
+    titles: Vec<Option<String>>,
+    by_stem: HashMap<String, Vec<usize>>,
+    by_title: HashMap<String, Vec<usize>>,
+    by_path: HashMap<String, usize>,
+}
+
+fn with_titles(dir: &Path, paths: Vec<PathBuf>) -> Vec<(String, Option<String>)> {
+    paths
+        .iter()
+        .map(|path| {
+            let title = fs::read_to_string(path)
+                .ok()
+                .and_then(|content| note_title(&content));
+            (relative_path(dir, path), title)
+        })
+        .collect()
+}
+
+fn key(text: &str) -> String {
+    text.trim().to_lowercase()
+}
+
+impl WikiResolver {
+    // `notes` are vault-relative paths with their titles
+    pub(crate) fn new(notes: Vec<(String, Option<String>)>) -> Self {
+        let mut resolver = WikiResolver {
+            notes: Vec::with_capacity(notes.len()),
+            titles: Vec::with_capacity(notes.len()),
+            by_stem: HashMap::new(),
+            by_title: HashMap::new(),
+            by_path: HashMap::new(),
+        };
+        for (i, (path, title)) in notes.into_iter().enumerate() {
+            resolver
+                .by_stem
+                .entry(key(file_stem(&path)))
+                .or_default()
+                .push(i);
+            if let Some(title) = &title {
+                resolver.by_title.entry(key(title)).or_default().push(i);
+            }
+            resolver.by_path.insert(key(strip_note_extension(&path)), i);
+            resolver.notes.push(path);
+            resolver.titles.push(title);
+        }
+        resolver
+    }
+
+    // Every active note in the vault, read once for its title
+    pub(crate) fn build(dir: &Path) -> Result<Self, String> {
+        Ok(WikiResolver::new(with_titles(dir, active_notes(dir)?)))
+    }
+
+    // The same notes once `old` has been renamed to `new`
+    pub(crate) fn renamed(&self, old: &str, new: &str) -> Self {
+        let notes = self
+            .notes
+            .iter()
+            .zip(&self.titles)
+            .map(|(path, title)| {
+                let path = if path == old { new } else { path };
+                (path.to_string(), title.clone())
+            })
+            .collect();
+        WikiResolver::new(notes)
+    }
+
+    fn pick(&self, matches: &[usize]) -> Result<&str, Unresolved> {
+        match matches {
+            [only] => Ok(&self.notes[*only]),
+            _ => Err(Unresolved::Ambiguous(
+                matches.iter().map(|&i| self.notes[i].clone()).collect(),
+            )),
+        }
+    }
+
+    pub(crate) fn resolve(&self, target: &str) -> Result<&str, Unresolved> {
+        let target = target.trim();
+        if !target.contains('/') {
+            if let Some(matches) = self.by_stem.get(&key(strip_note_extension(target))) {
+                return self.pick(matches);
+            }
+        }
+        if let Some(matches) = self.by_title.get(&key(target)) {
+            return self.pick(matches);
+        }
+        let path = strip_note_extension(target.trim_start_matches('/'));
+        match self.by_path.get(&key(path)) {
+            Some(&i) => Ok(&self.notes[i]),
+            None => Err(Unresolved::Missing),
+        }
+    }
+}
+
+// The note a `[[name]]` link points at, or why it doesn't point at exactly one
+#[tauri::command]
+pub async fn resolve_wikilink(name: String) -> Result<String, String> {
+    let target = name
+        .trim()
+        .trim_start_matches("[[")
+        .trim_end_matches("]]")
+        .split(['#', '|'])
+        .next()
+        .unwrap_or_default()
+        .trim()
+        .to_string();
+    if target.is_empty() {
+        return Err("Link has no target".to_string());
+    }
+
+    let dir = get_margherita_dir()?;
+    let resolver = WikiResolver::build(&dir)?;
+    resolver
+        .resolve(&target)
+        .map(|note| note.to_string())
+        .map_err(|e| e.describe(&target))
+}
@@ -0,0 +1,197 @@
+use chrono::{Duration, Local};
+use serde::Serialize;
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::path::Path;
+use std::sync::Mutex;
+use std::time::UNIX_EPOCH;
+
+use tauri::State;
+
+use crate::config::{read_config, update_config};
+use crate::get_margherita_dir;
+use crate::text::word_count;
+use crate::vault::{active_notes, relative_path, resolve_in_vault};
+
+const DEFAULT_DAYS: u32 = 30;
+
+#[derive(Debug, Serialize)]
+pub struct DayCount {
+    date: String,
+    words: usize,
+}
+
+// Words written since session_start, summed over the notes in the session
+#[derive(Debug, Serialize)]
+pub struct SessionDelta {
+    added: usize,
+    removed: usize,
+    net: i64,
+}
+
+// Word counts when the current writing session started, by note. A vault-wide session
+// counts notes created since as starting from zero
+struct Session {
+    baselines: HashMap<String, usize>,
+    vault_wide: bool,
+}
+
+// The focus session, kept in memory only
+#[derive(Default)]
+pub struct WritingSession {
+    current: Mutex<Option<Session>>,
+}
+
+fn modified_millis(path: &Path) -> u64 {
+    fs::metadata(path)
+        .and_then(|m| m.modified())
+        .ok()
+        .and_then(|t| t.duration_since(UNIX_EPOCH).ok())
+        .map(|d| d.as_millis() as u64)
+        .unwrap_or_default()
+}
+
+// Credit today with the words added to notes modified since the last check.
+// Notes seen for the first time only set a baseline, so old content isn't counted.
+pub(crate) fn record_word_counts() -> Result<(), String> {
+    let dir = get_margherita_dir()?;
+    if !dir.exists() {
+        return Ok(());
+    }
+    let notes = active_notes(&dir)?;
+    let now = chrono::Utc::now().timestamp_millis() as u64;
+    let today = Local::now().date_naive().to_string();
+
+    update_config(|config| {
+        let first_run = config.words_checked_at == 0;
+        let mut written = 0;
+
+        for path in &notes {
+            let name = relative_path(&dir, path);
+            let known = config.note_words.get(&name).copied();
+            if known.is_some() && modified_millis(path) < config.words_checked_at {
+                continue;
+            }
+            let Ok(content) = fs::read_to_string(path) else {
+                continue;
+            };
+
+            let words = word_count(&content);
+            match known {
+                Some(previous) => written += words.saturating_sub(previous),
+                None if !first_run => written += words,
+                None => {}
+            }
+            config.note_words.insert(name, words);
+        }
+
+        // Forget notes that no longer exist
+        let existing: HashSet<String> = notes.iter().map(|p| relative_path(&dir, p)).collect();
+        config.note_words.retain(|name, _| existing.contains(name));
+
+        if written > 0 {
+            *config.daily_words.entry(today).or_insert(0) += written;
+        }
+        config.words_checked_at = now;
+    })
+}
+
+// Words written per day over the last `days` days (30 by default), oldest first
+#[tauri::command]
+pub async fn daily_word_progress(days: Option<u32>) -> Result<Vec<DayCount>, String> {
+    record_word_counts()?;
+    let config = read_config()?;
+    let today = Local::now().date_naive();
+    let days = days.unwrap_or(DEFAULT_DAYS).max(1);
+
+    Ok((0..days)
+        .rev()
+        .map(|offset| {
+            let date = (today - Duration::days(offset as i64)).to_string();
+            let words = config.daily_words.get(&date).copied().unwrap_or(0);
+            DayCount { date, words }
+        })
+        .collect())
+}
+
+// Current word counts of one note, or of every active note
+fn current_counts(path: Option<&str>) -> Result<HashMap<String, usize>, String> {
+    let dir = get_margherita_dir()?;
+    let paths = match path {
+        Some(path) => vec![resolve_in_vault(path)?],
+        None => active_notes(&dir)?,
+    };
+
+    let mut counts = HashMap::new();
+    for path in paths {
+        let content =
+            fs::read_to_string(&path).map_err(|e| format!("Failed to read file: {}", e))?;
+        counts.insert(relative_path(&dir, &path), word_count(&content));
+    }
+    Ok(counts)
+}
+
+// Start a writing session on one note (or the whole vault, without `path`), replacing
+// any session already running. Returns the word count it starts from
+#[tauri::command]
+pub async fn session_start(
+    path: Option<String>,
+    session: State<'_, WritingSession>,
+) -> Result<usize, String> {
+    let baselines = current_counts(path.as_deref())?;
+    let total = baselines.values().sum();
+    *session.current.lock().map_err(|e| e.to_string())? = Some(Session {
+        baselines,
+        vault_wide: path.is_none(),
+    });
+    Ok(total)
+}
+
+// Words added and removed since session_start, in one note of the session or (without
+// `path`) across all of it
+#[tauri::command]
+pub async fn session_delta(
+    path: Option<String>,
+    session: State<'_, WritingSession>,
+) -> Result<SessionDelta, String> {
+    let current = session.current.lock().map_err(|e| e.to_string())?;
+    let session = current
+        .as_ref()
+        .ok_or_else(|| "No writing session has been started".to_string())?;
+
+    let counts = match &path {
+        Some(path) => current_counts(Some(path))?,
+        None if session.vault_wide => current_counts(None)?,
+        None => {
+            let note = session.baselines.keys().next().cloned();
+            current_counts(note.as_deref())?
+        }
+    };
+
+    let mut delta = SessionDelta {
+        added: 0,
+        removed: 0,
+        net: 0,
+    };
+    let mut seen = HashSet::new();
+    for (note, words) in &counts {
+        let before = match session.baselines.get(note) {
+            Some(before) => *before,
+            None if session.vault_wide => 0,
+            None => return Err(format!("{} isn't part of the writing session", note)),
+        };
+        delta.added += words.saturating_sub(before);
+        delta.removed += before.saturating_sub(*words);
+        seen.insert(note.as_str());
+    }
+    // Notes deleted during a vault-wide session take their words with them
+    if path.is_none() {
+        for (note, before) in &session.baselines {
+            if !seen.contains(note.as_str()) {
+                delta.removed += before;
+            }
+        }
+    }
+    delta.net = delta.added as i64 - delta.removed as i64;
+    Ok(delta)
+}